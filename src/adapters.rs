@@ -0,0 +1,100 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Joltage adapter chains from day 10
+use crate::count::CheckedCount;
+use color_eyre::eyre;
+
+/// Largest difference in joltage an adapter can take
+pub const MAX_STEP: u64 = 3;
+
+/// Every adapter in a bag, sorted, with the outlet at the start and the device at the end
+#[derive(Clone, Debug)]
+pub struct Chain {
+    ratings: Vec<u64>,
+}
+
+impl Chain {
+    /// Chains together every adapter, from the outlet at 0 jolts to a device rated 3 jolts above
+    /// the highest adapter
+    ///
+    /// # Errors
+    /// If two adapters have the same rating or there is a gap bigger than 3 jolts, either of
+    /// which makes it impossible to use every adapter
+    pub fn new(mut adapters: Vec<u64>) -> eyre::Result<Self> {
+        adapters.sort_unstable();
+        let device = adapters
+            .last()
+            .copied()
+            .unwrap_or(0)
+            .checked_add(MAX_STEP)
+            .ok_or_else(|| eyre::eyre!("Device rating overflowed"))?;
+        let mut ratings = Vec::with_capacity(adapters.len() + 2);
+        ratings.push(0);
+        ratings.extend(adapters);
+        ratings.push(device);
+        for pair in ratings.windows(2) {
+            match pair[1] - pair[0] {
+                0 => return Err(eyre::eyre!("Two adapters are rated {} jolts", pair[0])),
+                diff if diff > MAX_STEP => {
+                    return Err(eyre::eyre!(
+                        "Gap of {} jolts between {} and {}",
+                        diff,
+                        pair[0],
+                        pair[1]
+                    ))
+                }
+                _ => {}
+            }
+        }
+        Ok(Self { ratings })
+    }
+    /// Ratings along the chain, starting with the outlet and ending with the device
+    pub fn ratings(&self) -> &[u64] {
+        &self.ratings
+    }
+    /// Number of times each difference between neighbouring ratings occurs
+    ///
+    /// Index `n` holds the number of `n` jolt differences. Index 0 is always zero
+    pub fn histogram(&self) -> [usize; MAX_STEP as usize + 1] {
+        let mut histogram = [0; MAX_STEP as usize + 1];
+        for pair in self.ratings.windows(2) {
+            // Chain::new made sure every difference is between 1 and 3
+            histogram[(pair[1] - pair[0]) as usize] += 1;
+        }
+        histogram
+    }
+    /// Counts the ways adapters can be left out while still connecting the outlet to the device
+    ///
+    /// Each rating can be reached from any of the ratings up to 3 jolts below it, so the number of
+    /// ways to reach it is the sum of the ways to reach those
+    ///
+    /// # Returns
+    /// `None` if the count doesn't fit in `N`
+    pub fn arrangements<N: CheckedCount>(&self) -> Option<N> {
+        let mut ways: Vec<N> = Vec::with_capacity(self.ratings.len());
+        ways.push(N::one());
+        for (i, rating) in self.ratings.iter().enumerate().skip(1) {
+            let mut total = N::zero();
+            // Ratings are distinct, so at most 3 of the previous ones are close enough
+            for j in (0..i).rev() {
+                if rating - self.ratings[j] > MAX_STEP {
+                    break;
+                }
+                total = total.checked_add(&ways[j])?;
+            }
+            ways.push(total);
+        }
+        ways.pop()
+    }
+}
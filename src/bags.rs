@@ -0,0 +1,316 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Bag containment rules from day 7, stored as a graph
+mod analysis;
+mod export;
+mod parser;
+
+pub use crate::count::CheckedCount;
+pub use analysis::Analysis;
+pub use export::GraphFormat;
+pub use parser::{parse_rule, Rule};
+
+use color_eyre::eyre;
+use fnv::FnvHashMap;
+use std::collections::VecDeque;
+
+/// Interned bag color
+pub type ColorId = usize;
+
+/// Sums `count * memo[inner]` over a bag's contents
+fn weighted_sum<N: CheckedCount>(contents: &[(ColorId, usize)], memo: &[N]) -> Option<N> {
+    contents.iter().try_fold(N::zero(), |acc, (inner, count)| {
+        acc.checked_add(&memo[*inner].checked_mul_count(*count)?)
+    })
+}
+
+/// Directed graph of bag rules, with an edge from each bag to the bags it directly contains
+///
+/// A reverse index of the edges is kept alongside, so questions about what can hold a bag don't
+/// have to search from every color
+#[derive(Debug, Default)]
+pub struct BagGraph {
+    names: Vec<String>,
+    ids: FnvHashMap<String, ColorId>,
+    contents: Vec<Vec<(ColorId, usize)>>,
+    containers: Vec<Vec<(ColorId, usize)>>,
+    defined: Vec<bool>,
+}
+
+/// Visit state of a color during depth-first search
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    Unvisited,
+    InProgress,
+    Done,
+}
+
+impl BagGraph {
+    /// BagGraph constructor
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Gets the id of a color, creating one if the color hasn't been seen yet
+    pub fn intern(&mut self, color: &str) -> ColorId {
+        if let Some(id) = self.ids.get(color) {
+            *id
+        } else {
+            let id = self.names.len();
+            self.names.push(color.to_owned());
+            self.ids.insert(color.to_owned(), id);
+            self.contents.push(Vec::new());
+            self.containers.push(Vec::new());
+            self.defined.push(false);
+            id
+        }
+    }
+    /// Gets the id of a color if it has been seen
+    pub fn id(&self, color: &str) -> Option<ColorId> {
+        self.ids.get(color).copied()
+    }
+    /// Gets the name of a color
+    pub fn name(&self, id: ColorId) -> &str {
+        &self.names[id]
+    }
+    /// Number of distinct colors, whether defined or only referenced
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+    /// Checks whether the graph has no colors
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+    /// Checks whether a color has a rule of its own, rather than only appearing inside other rules
+    pub fn is_defined(&self, id: ColorId) -> bool {
+        self.defined[id]
+    }
+    /// Adds the rule for a color, replacing any earlier rule for it
+    pub fn add_rule<S: AsRef<str>>(&mut self, color: &str, contents: &[(S, usize)]) -> ColorId {
+        let id = self.intern(color);
+        let contents: Vec<(ColorId, usize)> = contents
+            .iter()
+            .map(|(inner, count)| (self.intern(inner.as_ref()), *count))
+            .collect();
+        // Drop the reverse edges of any rule being replaced
+        for (inner, _) in &self.contents[id] {
+            self.containers[*inner].retain(|(outer, _)| *outer != id);
+        }
+        for (inner, count) in &contents {
+            self.containers[*inner].push((id, *count));
+        }
+        self.contents[id] = contents;
+        self.defined[id] = true;
+        id
+    }
+    /// Bags directly contained in a bag, with their counts
+    pub fn contents(&self, id: ColorId) -> &[(ColorId, usize)] {
+        &self.contents[id]
+    }
+    /// Bags that directly contain a bag, with how many of it they hold
+    pub fn containers(&self, id: ColorId) -> &[(ColorId, usize)] {
+        &self.containers[id]
+    }
+    /// Orders the colors reachable from `roots` so that every bag comes after all of the bags it
+    /// contains
+    ///
+    /// The search is iterative, so deeply nested rules can't overflow the stack
+    ///
+    /// # Errors
+    /// If a cycle is found, the error names the colors in it
+    pub fn post_order<I: IntoIterator<Item = ColorId>>(
+        &self,
+        roots: I,
+    ) -> eyre::Result<Vec<ColorId>> {
+        self.find_post_order(roots).map_err(|cycle| {
+            let cycle = cycle
+                .iter()
+                .map(|color| self.name(*color))
+                .collect::<Vec<_>>();
+            eyre::eyre!("Cycle in bag rules: {}", cycle.join(" -> "))
+        })
+    }
+    /// Same as [`BagGraph::post_order`], but a cycle is returned as its colors, with the first one
+    /// repeated at the end
+    fn find_post_order<I: IntoIterator<Item = ColorId>>(
+        &self,
+        roots: I,
+    ) -> Result<Vec<ColorId>, Vec<ColorId>> {
+        let mut marks = vec![Mark::Unvisited; self.len()];
+        let mut order = Vec::with_capacity(self.len());
+        // Each entry is a color and the index of the next child to look at
+        let mut stack: Vec<(ColorId, usize)> = Vec::new();
+        for root in roots {
+            if marks[root] != Mark::Unvisited {
+                continue;
+            }
+            marks[root] = Mark::InProgress;
+            stack.push((root, 0));
+            while let Some((color, next)) = stack.last_mut() {
+                let color = *color;
+                match self.contents[color].get(*next) {
+                    Some((child, _)) => {
+                        *next += 1;
+                        match marks[*child] {
+                            Mark::Unvisited => {
+                                marks[*child] = Mark::InProgress;
+                                stack.push((*child, 0));
+                            }
+                            Mark::InProgress => {
+                                let start = stack.iter().position(|(c, _)| c == child).unwrap_or(0);
+                                return Err(stack[start..]
+                                    .iter()
+                                    .map(|(c, _)| *c)
+                                    .chain(std::iter::once(*child))
+                                    .collect());
+                            }
+                            Mark::Done => {}
+                        }
+                    }
+                    None => {
+                        marks[color] = Mark::Done;
+                        order.push(color);
+                        stack.pop();
+                    }
+                }
+            }
+        }
+        Ok(order)
+    }
+    /// Finds every color that can eventually contain `goal`, using a breadth-first search over the
+    /// reverse edges
+    ///
+    /// # Returns
+    /// The colors in the order they were found, nearest containers first
+    pub fn ancestors(&self, goal: ColorId) -> Vec<ColorId> {
+        let mut seen = vec![false; self.len()];
+        let mut found = Vec::new();
+        let mut queue = VecDeque::new();
+        seen[goal] = true;
+        queue.push_back(goal);
+        while let Some(color) = queue.pop_front() {
+            for (outer, _) in &self.containers[color] {
+                if !seen[*outer] {
+                    seen[*outer] = true;
+                    found.push(*outer);
+                    queue.push_back(*outer);
+                }
+            }
+        }
+        found
+    }
+    /// Finds every color that can eventually be found inside `root`, using a breadth-first search
+    ///
+    /// # Returns
+    /// The colors in the order they were found, outermost first
+    pub fn descendants(&self, root: ColorId) -> Vec<ColorId> {
+        let mut seen = vec![false; self.len()];
+        let mut found = Vec::new();
+        let mut queue = VecDeque::new();
+        seen[root] = true;
+        queue.push_back(root);
+        while let Some(color) = queue.pop_front() {
+            for (inner, _) in &self.contents[color] {
+                if !seen[*inner] {
+                    seen[*inner] = true;
+                    found.push(*inner);
+                    queue.push_back(*inner);
+                }
+            }
+        }
+        found
+    }
+    /// Finds which colors can hold `color` and which can be found inside it
+    ///
+    /// # Returns
+    /// Two vectors indexed by color id, for containers and contents, both including `color`
+    fn connected(&self, color: ColorId) -> (Vec<bool>, Vec<bool>) {
+        let mut up = vec![false; self.len()];
+        let mut down = vec![false; self.len()];
+        up[color] = true;
+        down[color] = true;
+        for other in self.ancestors(color) {
+            up[other] = true;
+        }
+        for other in self.descendants(color) {
+            down[other] = true;
+        }
+        (up, down)
+    }
+    /// Finds every color that can eventually contain `goal`, along with how many `goal` bags it
+    /// holds in total
+    ///
+    /// # Errors
+    /// If the rules have a cycle or a total doesn't fit in `N`
+    pub fn ancestor_multiplicities<N: CheckedCount>(
+        &self,
+        goal: ColorId,
+    ) -> eyre::Result<Vec<(ColorId, N)>> {
+        let ancestors = self.ancestors(goal);
+        let mut memo = vec![N::zero(); self.len()];
+        memo[goal] = N::one();
+        for color in self.post_order(ancestors.iter().copied())? {
+            if color != goal {
+                memo[color] = weighted_sum(&self.contents[color], &memo).ok_or_else(|| {
+                    eyre::eyre!(
+                        "Number of {} bags in {} overflowed",
+                        self.name(goal),
+                        self.name(color)
+                    )
+                })?;
+            }
+        }
+        Ok(ancestors
+            .into_iter()
+            .map(|color| (color, memo[color].clone()))
+            .collect())
+    }
+    /// Counts the bags in every bag reachable from `color`, including themselves
+    ///
+    /// # Errors
+    /// If `color` or any bag inside it isn't defined, the rules have a cycle, or a total doesn't
+    /// fit in `N`
+    fn bag_totals<N: CheckedCount>(&self, color: ColorId) -> eyre::Result<Vec<N>> {
+        if !self.defined[color] {
+            return Err(eyre::eyre!("No rule defines {} bags", self.name(color)));
+        }
+        let mut memo = vec![N::zero(); self.len()];
+        for color in self.post_order(std::iter::once(color))? {
+            // Counting an undefined bag as empty or as nothing would both be guesses
+            if !self.defined[color] {
+                return Err(eyre::eyre!("No rule defines {} bags", self.name(color)));
+            }
+            memo[color] = weighted_sum(&self.contents[color], &memo)
+                .and_then(|total| total.checked_add(&N::one()))
+                .ok_or_else(|| eyre::eyre!("Number of bags in {} overflowed", self.name(color)))?;
+        }
+        Ok(memo)
+    }
+    /// Counts the bags in a bag, including itself
+    ///
+    /// # Errors
+    /// If `color` or any bag inside it isn't defined, the rules have a cycle, or the total doesn't fit in `N`
+    pub fn bag_count<N: CheckedCount>(&self, color: ColorId) -> eyre::Result<N> {
+        let mut memo = self.bag_totals(color)?;
+        Ok(memo.swap_remove(color))
+    }
+    /// Counts the bags inside a bag, not including itself
+    ///
+    /// # Errors
+    /// If `color` or any bag inside it isn't defined, the rules have a cycle, or the total doesn't fit in `N`
+    pub fn bags_inside<N: CheckedCount>(&self, color: ColorId) -> eyre::Result<N> {
+        let memo = self.bag_totals(color)?;
+        weighted_sum(&self.contents[color], &memo)
+            .ok_or_else(|| eyre::eyre!("Number of bags in {} overflowed", self.name(color)))
+    }
+}
@@ -0,0 +1,128 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Linting and statistics for a bag rule set
+use super::{BagGraph, ColorId, Rule};
+use fnv::FnvHashMap;
+
+/// Problems and statistics found in a rule set
+#[derive(Debug)]
+pub struct Analysis {
+    /// Colors that appear inside rules but have no rule of their own
+    pub undefined: Vec<ColorId>,
+    /// Colors with more than one rule, along with the 1-based lines defining them
+    ///
+    /// The graph only keeps the last of these rules
+    pub duplicates: Vec<(ColorId, Vec<usize>)>,
+    /// Colors that can neither hold nor be held by the goal color, if there is one
+    pub unreachable: Option<Vec<ColorId>>,
+    /// Colors that no other bag holds
+    pub roots: Vec<ColorId>,
+    /// Colors that hold no other bags
+    pub leaves: Vec<ColorId>,
+    /// A cycle of bags that hold each other, with the first color repeated at the end
+    pub cycle: Option<Vec<ColorId>>,
+    /// Longest chain of directly nested bags, outermost first, if there is no cycle
+    pub longest_chain: Option<Vec<ColorId>>,
+    /// Every color, with each bag coming before all of the bags it holds, if there is no cycle
+    pub topological_order: Option<Vec<ColorId>>,
+}
+
+impl Analysis {
+    /// Analyzes a graph along with the rules, one per line, that it was built from
+    ///
+    /// A cycle is reported like any other finding. Only the chain and order need the rules to be
+    /// acyclic, so they are left out when there is one. Unreachable colors are only looked for
+    /// when there is a goal
+    pub fn new(rules: &[Rule], graph: &BagGraph, goal: Option<ColorId>) -> Self {
+        let mut definitions: FnvHashMap<&str, Vec<usize>> = FnvHashMap::default();
+        for (i, rule) in rules.iter().enumerate() {
+            definitions
+                .entry(rule.color.as_str())
+                .or_default()
+                .push(i + 1);
+        }
+        let mut duplicates: Vec<(ColorId, Vec<usize>)> = definitions
+            .into_iter()
+            .filter(|(_, lines)| lines.len() > 1)
+            .filter_map(|(color, lines)| Some((graph.id(color)?, lines)))
+            .collect();
+        duplicates.sort_unstable_by_key(|(_, lines)| lines[0]);
+        let colors = 0..graph.len();
+        // A color named only by a rule that a duplicate replaced isn't referenced any more
+        let undefined = colors
+            .clone()
+            .filter(|color| !graph.is_defined(*color) && !graph.containers(*color).is_empty())
+            .collect();
+        let unreachable = goal.map(|goal| {
+            let (up, down) = graph.connected(goal);
+            colors
+                .clone()
+                .filter(|color| !up[*color] && !down[*color])
+                .collect()
+        });
+        let roots = colors
+            .clone()
+            .filter(|color| graph.containers(*color).is_empty())
+            .collect();
+        let leaves = colors
+            .clone()
+            .filter(|color| graph.contents(*color).is_empty())
+            .collect();
+        let (cycle, longest_chain, topological_order) = match graph.find_post_order(colors) {
+            Ok(post_order) => {
+                let longest_chain = longest_chain(graph, &post_order);
+                let mut topological_order = post_order;
+                topological_order.reverse();
+                (None, Some(longest_chain), Some(topological_order))
+            }
+            Err(cycle) => (Some(cycle), None, None),
+        };
+        Self {
+            undefined,
+            duplicates,
+            unreachable,
+            roots,
+            leaves,
+            cycle,
+            longest_chain,
+            topological_order,
+        }
+    }
+}
+
+/// Finds the longest chain of directly nested bags, outermost first
+///
+/// Contents come first in `post_order`, so each bag's chain can be built from the chains inside it
+fn longest_chain(graph: &BagGraph, post_order: &[ColorId]) -> Vec<ColorId> {
+    let mut depth = vec![0; graph.len()];
+    let mut next: Vec<Option<ColorId>> = vec![None; graph.len()];
+    for color in post_order {
+        if let Some((inner, _)) = graph
+            .contents(*color)
+            .iter()
+            .max_by_key(|(inner, _)| depth[*inner])
+        {
+            next[*color] = Some(*inner);
+            depth[*color] = depth[*inner];
+        }
+        depth[*color] += 1;
+    }
+    let mut longest_chain = Vec::new();
+    let mut link = (0..graph.len()).max_by_key(|color| depth[*color]);
+    while let Some(color) = link {
+        longest_chain.push(color);
+        link = next[color];
+    }
+    longest_chain
+}
@@ -0,0 +1,159 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Rendering bag graphs as Graphviz DOT or Mermaid flowcharts
+use super::{BagGraph, ColorId};
+use color_eyre::eyre;
+use std::fmt;
+use std::str::FromStr;
+
+/// Output format for an exported graph
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+}
+
+impl FromStr for GraphFormat {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(Self::Dot),
+            "mermaid" => Ok(Self::Mermaid),
+            other => Err(eyre::eyre!("Invalid graph format: {}", other)),
+        }
+    }
+}
+
+/// Escapes a color name for use inside a double quoted string
+fn quote(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl BagGraph {
+    /// Renders the graph, with each edge labelled by its count
+    ///
+    /// If `highlight` is given, that color, every bag it can hold or be held by, and the edges
+    /// between them are drawn differently from the rest of the graph
+    pub fn export(&self, format: GraphFormat, highlight: Option<ColorId>) -> String {
+        let (up, down) = match highlight {
+            Some(color) => self.connected(color),
+            None => (vec![false; self.len()], vec![false; self.len()]),
+        };
+        Export {
+            graph: self,
+            format,
+            highlight,
+            up,
+            down,
+        }
+        .to_string()
+    }
+}
+
+/// A graph rendered in some format, along with what it connects to the highlighted color
+struct Export<'a> {
+    graph: &'a BagGraph,
+    format: GraphFormat,
+    highlight: Option<ColorId>,
+    /// Colors that can hold the highlighted color, and the colors it can hold
+    up: Vec<bool>,
+    down: Vec<bool>,
+}
+
+impl Export<'_> {
+    fn is_marked(&self, color: ColorId) -> bool {
+        self.up[color] || self.down[color]
+    }
+    /// Only edges on a path through the highlighted color count as part of its subgraph
+    fn is_marked_edge(&self, outer: ColorId, inner: ColorId) -> bool {
+        (self.up[outer] && self.up[inner]) || (self.down[outer] && self.down[inner])
+    }
+    fn fmt_dot(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let graph = self.graph;
+        writeln!(f, "digraph bags {{")?;
+        for color in 0..graph.len() {
+            write!(f, "    \"{}\"", quote(graph.name(color)))?;
+            if Some(color) == self.highlight {
+                write!(f, " [style=filled, fillcolor=gold]")?;
+            } else if self.is_marked(color) {
+                write!(f, " [style=filled, fillcolor=lightyellow]")?;
+            }
+            writeln!(f, ";")?;
+        }
+        for color in 0..graph.len() {
+            for (inner, count) in graph.contents(color) {
+                write!(
+                    f,
+                    "    \"{}\" -> \"{}\" [label=\"{}\"",
+                    quote(graph.name(color)),
+                    quote(graph.name(*inner)),
+                    count
+                )?;
+                if self.is_marked_edge(color, *inner) {
+                    write!(f, ", color=red, penwidth=2")?;
+                }
+                writeln!(f, "];")?;
+            }
+        }
+        writeln!(f, "}}")
+    }
+    fn fmt_mermaid(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let graph = self.graph;
+        writeln!(f, "flowchart LR")?;
+        for color in 0..graph.len() {
+            writeln!(f, "    n{}[\"{}\"]", color, quote(graph.name(color)))?;
+        }
+        let mut edge = 0;
+        let mut marked_edges = Vec::new();
+        for color in 0..graph.len() {
+            for (inner, count) in graph.contents(color) {
+                writeln!(f, "    n{} -->|{}| n{}", color, count, inner)?;
+                if self.is_marked_edge(color, *inner) {
+                    marked_edges.push(edge.to_string());
+                }
+                edge += 1;
+            }
+        }
+        if let Some(highlight) = self.highlight {
+            writeln!(f, "    classDef goal fill:gold;")?;
+            writeln!(f, "    classDef connected fill:lightyellow;")?;
+            writeln!(f, "    class n{} goal;", highlight)?;
+            let connected = (0..graph.len())
+                .filter(|color| *color != highlight && self.is_marked(*color))
+                .map(|color| format!("n{}", color))
+                .collect::<Vec<_>>();
+            if !connected.is_empty() {
+                writeln!(f, "    class {} connected;", connected.join(","))?;
+            }
+            if !marked_edges.is_empty() {
+                writeln!(
+                    f,
+                    "    linkStyle {} stroke:red,stroke-width:2px;",
+                    marked_edges.join(",")
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Export<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.format {
+            GraphFormat::Dot => self.fmt_dot(f),
+            GraphFormat::Mermaid => self.fmt_mermaid(f),
+        }
+    }
+}
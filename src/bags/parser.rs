@@ -0,0 +1,275 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Parser for the bag rule language
+//!
+//! ```text
+//! rule     := color bag "contain" contents "."
+//! contents := "no" "other" bag | item ("," item)*
+//! item     := number color bag
+//! color    := word+
+//! bag      := "bag" | "bags"
+//! ```
+//!
+//! "bag" and "bags" are interchangeable everywhere, as are "contain" and "contains"
+use color_eyre::eyre;
+use std::fmt;
+use std::str::FromStr;
+
+/// A single parsed rule
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    pub color: String,
+    pub contents: Vec<(String, usize)>,
+}
+
+impl fmt::Display for Rule {
+    /// Writes the rule back out in canonical form, using "bag" only for a count of 1
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bags contain ", self.color)?;
+        if self.contents.is_empty() {
+            write!(f, "no other bags")?;
+        }
+        for (i, (color, count)) in self.contents.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            let bag = if *count == 1 { "bag" } else { "bags" };
+            write!(f, "{} {} {}", count, color, bag)?;
+        }
+        write!(f, ".")
+    }
+}
+
+impl FromStr for Rule {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_rule(s, 1)
+    }
+}
+
+#[derive(Debug)]
+enum TokenKind {
+    Word(String),
+    Number(usize),
+    Comma,
+    Period,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Word(word) => write!(f, "{:?}", word),
+            Self::Number(n) => write!(f, "{}", n),
+            Self::Comma => write!(f, "\",\""),
+            Self::Period => write!(f, "\".\""),
+        }
+    }
+}
+
+/// Token along with the 1-based column it starts at
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    column: usize,
+}
+
+/// Splits a line into tokens
+fn tokenize(s: &str, line: usize) -> eyre::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().enumerate().peekable();
+    while let Some((i, c)) = chars.next() {
+        let column = i + 1;
+        let kind = match c {
+            c if c.is_whitespace() => continue,
+            ',' => TokenKind::Comma,
+            '.' => TokenKind::Period,
+            c if c.is_ascii_digit() => {
+                let mut digits = c.to_string();
+                while let Some((_, c)) = chars.peek().filter(|(_, c)| c.is_ascii_digit()) {
+                    digits.push(*c);
+                    chars.next();
+                }
+                TokenKind::Number(digits.parse().map_err(|_| {
+                    eyre::eyre!("{}:{}: count {} is too large", line, column, digits)
+                })?)
+            }
+            c if c.is_alphabetic() => {
+                let mut word = c.to_string();
+                while let Some((_, c)) = chars
+                    .peek()
+                    .filter(|(_, c)| c.is_alphabetic() || *c == '-' || *c == '\'')
+                {
+                    word.push(*c);
+                    chars.next();
+                }
+                TokenKind::Word(word)
+            }
+            c => {
+                return Err(eyre::eyre!(
+                    "{}:{}: unexpected character {:?}",
+                    line,
+                    column,
+                    c
+                ))
+            }
+        };
+        tokens.push(Token { kind, column });
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser over the tokens of one line
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    line: usize,
+    /// Column just past the end of the line, for errors about missing tokens
+    end_column: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+    /// Builds an error pointing at the current token
+    fn error(&self, expected: &str) -> eyre::Report {
+        match self.peek() {
+            Some(token) => eyre::eyre!(
+                "{}:{}: expected {}, found {}",
+                self.line,
+                token.column,
+                expected,
+                token.kind
+            ),
+            None => eyre::eyre!(
+                "{}:{}: expected {}, found end of line",
+                self.line,
+                self.end_column,
+                expected
+            ),
+        }
+    }
+    /// Consumes a word if it is one of `words`
+    fn eat_word(&mut self, words: &[&str]) -> bool {
+        match self.peek() {
+            Some(Token {
+                kind: TokenKind::Word(word),
+                ..
+            }) if words.contains(&word.as_str()) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+    fn expect_word(&mut self, words: &[&str], expected: &str) -> eyre::Result<()> {
+        if self.eat_word(words) {
+            Ok(())
+        } else {
+            Err(self.error(expected))
+        }
+    }
+    /// Parses a color and the "bag" or "bags" after it
+    fn color(&mut self) -> eyre::Result<String> {
+        let mut words = Vec::new();
+        loop {
+            if self.eat_word(&["bag", "bags"]) {
+                break;
+            }
+            match self.peek() {
+                Some(Token {
+                    kind: TokenKind::Word(word),
+                    ..
+                }) => {
+                    words.push(word.clone());
+                    self.pos += 1;
+                }
+                _ if words.is_empty() => return Err(self.error("a color")),
+                _ => return Err(self.error("\"bag\" or \"bags\"")),
+            }
+        }
+        if words.is_empty() {
+            // Step back so the error points at the "bag" with no color before it
+            self.pos -= 1;
+            return Err(self.error("a color"));
+        }
+        Ok(words.join(" "))
+    }
+    /// Parses a count followed by a color
+    fn item(&mut self) -> eyre::Result<(String, usize)> {
+        let count = match self.peek() {
+            Some(Token {
+                kind: TokenKind::Number(0),
+                column,
+            }) => {
+                return Err(eyre::eyre!(
+                    "{}:{}: count must be at least 1",
+                    self.line,
+                    column
+                ))
+            }
+            Some(Token {
+                kind: TokenKind::Number(count),
+                ..
+            }) => *count,
+            _ => return Err(self.error("a count")),
+        };
+        self.pos += 1;
+        Ok((self.color()?, count))
+    }
+    fn rule(&mut self) -> eyre::Result<Rule> {
+        let color = self.color()?;
+        self.expect_word(&["contain", "contains"], "\"contain\"")?;
+        let mut contents = Vec::new();
+        if self.eat_word(&["no"]) {
+            self.expect_word(&["other"], "\"other\"")?;
+            self.expect_word(&["bag", "bags"], "\"bags\"")?;
+        } else {
+            loop {
+                contents.push(self.item()?);
+                match self.peek() {
+                    Some(Token {
+                        kind: TokenKind::Comma,
+                        ..
+                    }) => self.pos += 1,
+                    _ => break,
+                }
+            }
+        }
+        match self.peek() {
+            Some(Token {
+                kind: TokenKind::Period,
+                ..
+            }) => self.pos += 1,
+            _ => return Err(self.error("\",\" or \".\"")),
+        }
+        if self.peek().is_some() {
+            return Err(self.error("end of line"));
+        }
+        Ok(Rule { color, contents })
+    }
+}
+
+/// Parses one rule, reporting errors against the given 1-based line number
+pub fn parse_rule(s: &str, line: usize) -> eyre::Result<Rule> {
+    let tokens = tokenize(s, line)?;
+    Parser {
+        tokens,
+        pos: 0,
+        line,
+        end_column: s.chars().count() + 1,
+    }
+    .rule()
+}
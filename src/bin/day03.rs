@@ -11,33 +11,30 @@
 //
 // You should have received a copy of the GNU General Public
 // License along with this program. If not, see <https://www.gnu.org/licenses/>.
+use aoc2020::grid::Grid;
 use color_eyre::eyre::{self, WrapErr};
 
-use std::io::{self, BufRead};
+use std::io::{self, Read};
 
 use std::time::Instant;
 
 fn main() -> eyre::Result<()> {
     color_eyre::install()?;
-    let stdin = io::stdin();
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .wrap_err("Failed to read input")?;
     // Start the timer
     let start_time = Instant::now();
     // Solve the problem
-    let grid = stdin
-        .lock()
-        .lines()
-        .map(|line| match line {
-            Ok(line) => line
-                .chars()
-                .map(|c| match c {
-                    '.' => Ok(false),
-                    '#' => Ok(true),
-                    _ => Err(eyre::eyre!("Invalid character")),
-                })
-                .collect::<Result<Vec<bool>, _>>(),
-            Err(err) => Err(err).wrap_err("Failed to read line"),
-        })
-        .collect::<Result<Vec<Vec<bool>>, _>>()?;
+    let grid = Grid::parse(&input, |c| match c {
+        '.' => Some(false),
+        '#' => Some(true),
+        _ => None,
+    })?;
+    if grid.width() == 0 {
+        return Err(eyre::eyre!("Map is empty"));
+    }
     // List of slopes in part 2
     const PART2_SLOPES: [(usize, usize); 5] = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)];
     // Index in the part 2 slopes list that references the part 1 slope
@@ -46,8 +43,8 @@ fn main() -> eyre::Result<()> {
     for (i, (x_slope, y_slope)) in PART2_SLOPES.iter().enumerate() {
         let mut trees = 0;
         let mut x = 0;
-        for row in grid.iter().step_by(*y_slope) {
-            if row[x % row.len()] {
+        for y in (0..grid.height()).step_by(*y_slope) {
+            if grid.get(x % grid.width(), y) == Some(&true) {
                 trees += 1;
             }
             x += x_slope;
@@ -151,6 +151,7 @@ impl FromStr for EyeColor {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 enum Height {
     Inches(u8),
@@ -188,6 +189,7 @@ impl FromStr for Height {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 struct Passport {
     byr: u16,
@@ -213,7 +215,7 @@ fn main() -> eyre::Result<()> {
         .chain(vec![Ok("".into())])
         .scan(
             PassportIncomplete::default(),
-            |mut state, line| match line {
+            |state, line| match line {
                 Ok(line) => {
                     if line.is_empty() {
                         let passport = state.clone();
@@ -12,66 +12,190 @@
 // You should have received a copy of the GNU General Public
 // License along with this program. If not, see <https://www.gnu.org/licenses/>.
 use color_eyre::eyre::{self, WrapErr};
-use std::convert::TryInto;
 use std::io::{self, BufRead};
 use std::time::Instant;
 
+/// Number of rows on the plane
+const ROWS: usize = 128;
+/// Number of seats in each row
+const COLS: u16 = 8;
+
+/// Length of a boarding pass, 7 row characters followed by 3 column characters
+const PASS_LEN: usize = 10;
+
+/// Decodes a boarding pass into its row and column
+fn parse_pass(pass: &str) -> eyre::Result<(u8, u8)> {
+    if pass.chars().count() != PASS_LEN {
+        return Err(eyre::eyre!(
+            "Boarding pass {:?} must be exactly {} characters long",
+            pass,
+            PASS_LEN
+        ));
+    }
+    // Row is just binary, F=0, B=1
+    let row: u8 = pass.chars().take(7).try_fold(0, |acc, c| match c {
+        'F' => Ok(acc * 2),
+        'B' => Ok(acc * 2 + 1),
+        c => Err(eyre::eyre!("Invalid row character: {}", c)),
+    })?;
+    // Col is also just binary
+    // R=1 L=0
+    let col: u8 = pass.chars().skip(7).try_fold(0, |acc, c| match c {
+        'L' => Ok(acc * 2),
+        'R' => Ok(acc * 2 + 1),
+        c => Err(eyre::eyre!("Invalid column character: {}", c)),
+    })?;
+    Ok((row, col))
+}
+
+/// Occupancy bitmap of the plane, one byte per row with bit `n` set if column `n` is taken
+struct SeatMap {
+    rows: [u8; ROWS],
+    /// Boarding passes that claimed an already occupied seat, along with that seat's id
+    duplicates: Vec<(String, u16)>,
+}
+
+impl SeatMap {
+    /// SeatMap constructor
+    fn new() -> Self {
+        Self {
+            rows: [0; ROWS],
+            duplicates: Vec::new(),
+        }
+    }
+    /// Marks a seat as occupied
+    ///
+    /// # Returns
+    /// `true` if the seat was empty
+    /// `false` if the seat was already occupied, in which case the pass is recorded as a duplicate
+    fn insert(&mut self, pass: &str, row: u8, col: u8) -> bool {
+        let mask = 1 << col;
+        let row_bits = &mut self.rows[usize::from(row)];
+        if *row_bits & mask != 0 {
+            self.duplicates
+                .push((pass.to_owned(), u16::from(row) * COLS + u16::from(col)));
+            false
+        } else {
+            *row_bits |= mask;
+            true
+        }
+    }
+    /// Checks whether the seat with the given id is occupied
+    fn is_occupied(&self, id: u16) -> bool {
+        self.rows[usize::from(id / COLS)] & (1 << (id % COLS)) != 0
+    }
+    /// Finds our seat, the only empty seat strictly between the lowest and highest taken ids
+    fn find_seat(&self, min: u16, max: u16) -> eyre::Result<u16> {
+        let candidates: Vec<u16> = (min.saturating_add(1)..max)
+            .filter(|id| !self.is_occupied(*id))
+            .collect();
+        match candidates.as_slice() {
+            [] => Err(eyre::eyre!(
+                "No empty seat between seats {} and {}",
+                min,
+                max
+            )),
+            [id] => Ok(*id),
+            ids => Err(eyre::eyre!("Multiple candidate seats: {:?}", ids)),
+        }
+    }
+    /// Range of rows containing at least one occupied seat
+    ///
+    /// Rows outside of this range are assumed to not exist on the plane
+    fn occupied_rows(&self) -> Option<(usize, usize)> {
+        let first = self.rows.iter().position(|row| *row != 0)?;
+        let last = self.rows.iter().rposition(|row| *row != 0)?;
+        Some((first, last))
+    }
+    /// Iterates over the ids of all empty seats within the occupied rows
+    fn empty_seats(&self) -> impl Iterator<Item = u16> + '_ {
+        let (first, last) = self.occupied_rows().unwrap_or((1, 0));
+        (first..=last).flat_map(move |row| {
+            let bits = self.rows[row];
+            (0..COLS)
+                .filter(move |col| bits & (1 << col) == 0)
+                // row is below ROWS, so this can't overflow
+                .map(move |col| row as u16 * COLS + col)
+        })
+    }
+    /// Renders the occupied rows as ASCII, `#` for taken seats and `.` for empty ones
+    fn render(&self) -> String {
+        let mut out = String::new();
+        if let Some((first, last)) = self.occupied_rows() {
+            for row in first..=last {
+                out.push_str(&format!("{:>3} ", row));
+                for col in 0..COLS {
+                    out.push(if self.rows[row] & (1 << col) != 0 {
+                        '#'
+                    } else {
+                        '.'
+                    });
+                }
+                out.push('\n');
+            }
+        }
+        out
+    }
+    /// Prints the seat map, empty seats, per-row fill rates and duplicate passes
+    fn print_report(&self) {
+        println!("Seat map:");
+        print!("{}", self.render());
+        let empty: Vec<String> = self.empty_seats().map(|id| id.to_string()).collect();
+        println!("Empty seats ({}): {}", empty.len(), empty.join(", "));
+        println!("Row fill rates:");
+        if let Some((first, last)) = self.occupied_rows() {
+            for row in first..=last {
+                let taken = self.rows[row].count_ones();
+                println!(
+                    "{:>3} {}/{} ({:.1}%)",
+                    row,
+                    taken,
+                    COLS,
+                    f64::from(taken) * 100.0 / f64::from(COLS)
+                );
+            }
+        }
+        println!("Duplicate boarding passes ({}):", self.duplicates.len());
+        for (pass, id) in &self.duplicates {
+            println!("    {} (seat {})", pass, id);
+        }
+    }
+}
+
 fn main() -> eyre::Result<()> {
     color_eyre::install()?;
+    // Print the full seat report if requested
+    let report = std::env::args().skip(1).any(|arg| arg == "--report");
     let stdin = io::stdin();
     // Start the timer
     let start_time = Instant::now();
     // Solve the problem
-    let (min, max, mut map) =
-        stdin
-            .lock()
-            .lines()
-            .try_fold(
-                (0u16, 0u16, [0u8; 128]),
-                |(min, max, mut map), line| match line {
-                    Ok(line) => {
-                        // Row is just binary, F=0, B=1
-                        let row: u8 = line.chars().take(7).try_fold(0, |acc, c| match c {
-                            'F' => Ok(acc * 2),
-                            'B' => Ok(acc * 2 + 1),
-                            c => Err(eyre::eyre!("Invalid character: {}", c)),
-                        })?;
-                        // Col is also just binary
-                        // R=1 L=0
-                        let col: u8 =
-                            line.chars().skip(7).take(3).try_fold(0, |acc, c| match c {
-                                'L' => Ok(acc * 2),
-                                'R' => Ok(acc * 2 + 1),
-                                c => Err(eyre::eyre!("Invalid character: {}", c)),
-                            })?;
-                        // Set the bit
-                        let mask = 1 << col;
-                        map[usize::from(row)] = (map[usize::from(row)] & !mask) | mask;
-                        // Get the id
-                        let id = u16::from(row) * 8 + u16::from(col);
-                        // Update minimum and maximum
-                        Ok((min.min(id), max.max(id), map))
-                    }
-                    Err(err) => Err(err).wrap_err("Failed to read line"),
-                },
-            )?;
+    let (min, max, map) = stdin.lock().lines().enumerate().try_fold(
+        (u16::MAX, 0u16, SeatMap::new()),
+        |(min, max, mut map), (i, line)| match line {
+            Ok(line) => {
+                let (row, col) = parse_pass(&line)
+                    .wrap_err_with(|| format!("Failed to parse line {}", i + 1))?;
+                // Set the bit
+                map.insert(&line, row, col);
+                // Get the id
+                let id = u16::from(row) * COLS + u16::from(col);
+                // Update minimum and maximum
+                Ok((min.min(id), max.max(id), map))
+            }
+            Err(err) => Err(err).wrap_err("Failed to read line"),
+        },
+    )?;
+    if min > max {
+        return Err(eyre::eyre!("No boarding passes given"));
+    }
     println!("Part 1: {}", max);
-    // Set the ends of the map to 255
-    // This "fills" seats at the beginning and end because we can assume the seats aren't ours
-    map[usize::from(min / 8)] |= !((1 << (min % 8)) - 1);
-    map[usize::from(max / 8)] = 0b11111111;
-    // Search for our seat
-    let part2_row = map[usize::from(min / 8)..=usize::from(max / 8)]
-        .iter()
-        .position(|b| *b != 0b11111111)
-        .ok_or_else(|| eyre::eyre!("Failed to find our seat"))?;
-    let part2_col: usize = map[part2_row]
-        .trailing_ones()
-        .try_into()
-        .wrap_err("Failed to convert col to usize somehow")?;
-    println!("Part 2: {}", part2_row * 8 + part2_col);
+    println!("Part 2: {}", map.find_seat(min, max)?);
     // Stop the timer
     let time_elapsed = start_time.elapsed();
     println!("Solved in {:?}", time_elapsed);
+    if report {
+        map.print_report();
+    }
     Ok(())
 }
@@ -12,72 +12,286 @@
 // You should have received a copy of the GNU General Public
 // License along with this program. If not, see <https://www.gnu.org/licenses/>.
 use color_eyre::eyre::{self, WrapErr};
-use std::convert::TryInto;
+use fnv::FnvHashMap;
 use std::io::{self, BufRead};
+use std::str::FromStr;
 use std::time::Instant;
 
+/// Compact set of answered questions, bit `n` is set if question `n` was answered
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct AnswerSet(u32);
+
+impl AnswerSet {
+    /// Maximum number of distinct questions a set can hold
+    const CAPACITY: usize = 32;
+
+    /// Set containing every question
+    fn full() -> Self {
+        Self(u32::MAX)
+    }
+    /// Adds a question to the set
+    fn insert(&mut self, question: usize) {
+        self.0 |= 1 << question;
+    }
+    /// Checks whether a question is in the set
+    fn contains(self, question: usize) -> bool {
+        self.0 & (1 << question) != 0
+    }
+    fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+    fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+    fn symmetric_difference(self, other: Self) -> Self {
+        Self(self.0 ^ other.0)
+    }
+    /// Number of questions in the set
+    fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+}
+
+/// Answers given by each person in a group
+struct Group {
+    people: Vec<AnswerSet>,
+}
+
+impl Group {
+    /// Questions anyone in the group answered
+    fn union(&self) -> AnswerSet {
+        self.people
+            .iter()
+            .fold(AnswerSet::default(), |acc, p| acc.union(*p))
+    }
+    /// Questions everyone in the group answered
+    fn intersection(&self) -> AnswerSet {
+        self.people
+            .iter()
+            .fold(AnswerSet::full(), |acc, p| acc.intersection(*p))
+    }
+    /// Questions answered by an odd number of people in the group
+    fn symmetric_difference(&self) -> AnswerSet {
+        self.people
+            .iter()
+            .fold(AnswerSet::default(), |acc, p| acc.symmetric_difference(*p))
+    }
+    /// Number of people who answered each question
+    fn frequencies(&self) -> [usize; AnswerSet::CAPACITY] {
+        let mut freqs = [0; AnswerSet::CAPACITY];
+        for person in &self.people {
+            for (question, freq) in freqs.iter_mut().enumerate() {
+                if person.contains(question) {
+                    *freq += 1;
+                }
+            }
+        }
+        freqs
+    }
+    /// Questions among the first `questions` answered by at least `k` people in the group
+    fn at_least(&self, k: usize, questions: usize) -> AnswerSet {
+        let mut set = AnswerSet::default();
+        for (question, freq) in self.frequencies().iter().enumerate().take(questions) {
+            if *freq >= k {
+                set.insert(question);
+            }
+        }
+        set
+    }
+}
+
+/// How answers within a group are aggregated
+#[derive(Debug)]
+enum Mode {
+    Union,
+    Intersection,
+    SymmetricDifference,
+    AtLeast(usize),
+    Histogram,
+}
+
+impl FromStr for Mode {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "union" => Ok(Self::Union),
+            "intersection" => Ok(Self::Intersection),
+            "symdiff" => Ok(Self::SymmetricDifference),
+            "histogram" => Ok(Self::Histogram),
+            other => match other.strip_prefix("at-least=") {
+                Some(k) => k
+                    .parse()
+                    .map(Self::AtLeast)
+                    .wrap_err_with(|| format!("Invalid count in mode {:?}", other)),
+                None => Err(eyre::eyre!("Invalid mode: {}", other)),
+            },
+        }
+    }
+}
+
+impl Mode {
+    /// Aggregates a group into a single set, or `None` for modes that don't produce one
+    ///
+    /// `questions` is the size of the alphabet, so questions outside of it are never counted
+    fn aggregate(&self, group: &Group, questions: usize) -> Option<AnswerSet> {
+        match self {
+            Self::Union => Some(group.union()),
+            Self::Intersection => Some(group.intersection()),
+            Self::SymmetricDifference => Some(group.symmetric_difference()),
+            Self::AtLeast(k) => Some(group.at_least(*k, questions)),
+            Self::Histogram => None,
+        }
+    }
+}
+
+/// Characters that may appear as answers, in question order
+struct Alphabet {
+    chars: Vec<char>,
+    indices: FnvHashMap<char, usize>,
+}
+
+impl Alphabet {
+    /// Alphabet used by the puzzle input
+    const DEFAULT: &'static str = "abcdefghijklmnopqrstuvwxyz";
+
+    /// Converts a question's character into its index
+    fn index(&self, c: char) -> Option<usize> {
+        self.indices.get(&c).copied()
+    }
+    /// Converts a question's index back into its character
+    fn char(&self, question: usize) -> char {
+        self.chars[question]
+    }
+}
+
+impl FromStr for Alphabet {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let chars: Vec<char> = s.chars().collect();
+        if chars.is_empty() {
+            return Err(eyre::eyre!("Alphabet is empty"));
+        }
+        if chars.len() > AnswerSet::CAPACITY {
+            return Err(eyre::eyre!(
+                "Alphabet has {} characters but at most {} are supported",
+                chars.len(),
+                AnswerSet::CAPACITY
+            ));
+        }
+        let mut indices = FnvHashMap::default();
+        for (i, c) in chars.iter().enumerate() {
+            if indices.insert(*c, i).is_some() {
+                return Err(eyre::eyre!("Alphabet contains {:?} more than once", c));
+            }
+        }
+        Ok(Self { chars, indices })
+    }
+}
+
 fn main() -> eyre::Result<()> {
     color_eyre::install()?;
+    // Parse the aggregation modes given on the command line
+    let mut modes = Vec::new();
+    let mut alphabet: Alphabet = Alphabet::DEFAULT.parse()?;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--alphabet" => {
+                alphabet = args
+                    .next()
+                    .ok_or_else(|| eyre::eyre!("--alphabet requires a value"))?
+                    .parse()
+                    .wrap_err("Invalid alphabet")?
+            }
+            "--mode" => modes.push(
+                args.next()
+                    .ok_or_else(|| eyre::eyre!("--mode requires a value"))?
+                    .parse::<Mode>()?,
+            ),
+            other => return Err(eyre::eyre!("Unknown argument: {}", other)),
+        }
+    }
     let stdin = io::stdin();
     // Start the timer
     let start_time = Instant::now();
-    // Read in part 1 valid passports
-    let (part1, part2) = stdin
+    // Read in the groups
+    let groups = stdin
         .lock()
         .lines()
         // Add an extra empty line at the end
         .chain(vec![Ok("".into())])
-        .scan(([false; 26], [true; 26]), |(seen, common), line| {
-            match line {
-                Ok(line) => {
-                    if line.is_empty() {
-                        let answer = Some(Ok(Some((
-                            seen.iter().map(|b| if *b { 1 } else { 0 }).sum::<usize>(),
-                            common.iter().map(|b| if *b { 1 } else { 0 }).sum::<usize>(),
-                        ))));
-                        *seen = [false; 26];
-                        *common = [true; 26];
-                        answer
+        .enumerate()
+        .scan(Vec::new(), |people, (i, line)| match line {
+            Ok(line) => {
+                if line.is_empty() {
+                    if people.is_empty() {
+                        Some(Ok(None))
                     } else {
-                        let mut cur_line = [false; 26];
-                        for c in line.chars() {
-                            // Convert character to base 36 number and subtract 10 to get a 0-26 number
-                            let c_num: usize = if let Some(c_num) = c.to_digit(36) {
-                                match (c_num - 10).try_into() {
-                                    Ok(c_num) => c_num,
-                                    Err(err) => {
-                                        return Some(Err(err).wrap_err(
-                                            "Failed to convert character index to usize",
-                                        ))
-                                    }
-                                }
-                            } else {
-                                return Some(Err(eyre::eyre!("Invalid letter")));
-                            };
-                            seen[c_num] = true;
-                            cur_line[c_num] = true;
-                        }
-                        for (common, cur) in common.iter_mut().zip(&cur_line) {
-                            if !cur {
-                                *common = false;
+                        Some(Ok(Some(Group {
+                            people: std::mem::take(people),
+                        })))
+                    }
+                } else {
+                    let mut person = AnswerSet::default();
+                    for (col, c) in line.chars().enumerate() {
+                        match alphabet.index(c) {
+                            Some(question) => person.insert(question),
+                            None => {
+                                return Some(Err(eyre::eyre!(
+                                "Character {:?} at line {}, column {} is not in the alphabet {:?}",
+                                c,
+                                i + 1,
+                                col + 1,
+                                alphabet.chars.iter().collect::<String>()
+                            )))
                             }
                         }
-                        Some(Ok(None))
                     }
+                    people.push(person);
+                    Some(Ok(None))
                 }
-                Err(err) => Some(Err(err).wrap_err("Failed to read line")),
             }
+            Err(err) => Some(Err(err).wrap_err("Failed to read line")),
         })
-        .filter_map(|line| match line {
-            Ok(Some(line)) => Some(Ok(line)),
+        .filter_map(|group| match group {
+            Ok(Some(group)) => Some(Ok(group)),
             Ok(None) => None,
             Err(err) => Some(Err(err)),
         })
-        .try_fold((0, 0), |(seen, common), cur_info| {
-            cur_info.map(|(cur_seen, cur_common)| (seen + cur_seen, common + cur_common))
-        })?;
-    println!("Part 1: {}", part1);
-    println!("Part 2: {}", part2);
+        .collect::<eyre::Result<Vec<Group>>>()?;
+    if modes.is_empty() {
+        let part1: usize = groups.iter().map(|group| group.union().len()).sum();
+        println!("Part 1: {}", part1);
+        let part2: usize = groups.iter().map(|group| group.intersection().len()).sum();
+        println!("Part 2: {}", part2);
+    }
+    for mode in &modes {
+        match mode {
+            Mode::Histogram => {
+                for (i, group) in groups.iter().enumerate() {
+                    let freqs = group
+                        .frequencies()
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, freq)| **freq > 0)
+                        .map(|(question, freq)| format!("{}={}", alphabet.char(question), freq))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    println!("Group {}: {}", i, freqs);
+                }
+            }
+            mode => {
+                let total: usize = groups
+                    .iter()
+                    .filter_map(|group| mode.aggregate(group, alphabet.chars.len()))
+                    .map(AnswerSet::len)
+                    .sum();
+                println!("{:?}: {}", mode, total);
+            }
+        }
+    }
     // Stop the timer
     let time_elapsed = start_time.elapsed();
     println!("Solved in {:?}", time_elapsed);
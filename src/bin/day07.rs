@@ -11,74 +11,81 @@
 //
 // You should have received a copy of the GNU General Public
 // License along with this program. If not, see <https://www.gnu.org/licenses/>.
+use aoc2020::bags::{parse_rule, Analysis, BagGraph, CheckedCount, ColorId, GraphFormat, Rule};
 use color_eyre::eyre::{self, WrapErr};
-use std::collections::HashMap;
 use std::io::{self, BufRead};
 use std::time::Instant;
 
-// This is contained in a function because rust couldn't figure out its type bounds
-fn parse_bag_contents_item(s: &str) -> eyre::Result<Option<(String, usize)>> {
-    let bag = s.trim_end_matches('s').trim_end_matches(" bag");
-    let mut bag = bag.split(' ');
-    let count = bag
-        .next()
-        .map(|s| if s == "no" { "0" } else { s })
-        .ok_or_else(|| eyre::eyre!("Missing bag count"))?
-        .parse()
-        .wrap_err("Failed to parse bag count")?;
-    if count == 0 {
-        Ok(None)
-    } else {
-        //TODO: change this once split_once is stable
-        let color = bag.collect::<Vec<&str>>().join(" ");
-        if color.is_empty() {
-            Err(eyre::eyre!("Bag color is empty"))
-        } else {
-            Ok(Some((color, count)))
-        }
-    }
+fn part1(graph: &BagGraph, goal: ColorId) -> usize {
+    graph.ancestors(goal).len()
 }
 
-fn part1(rules: &HashMap<String, Vec<(String, usize)>>, goal_color: &str) -> usize {
-    rules
-        .keys()
-        .map(|color| can_contain(rules, color, goal_color))
-        .map(|x| if x { 1 } else { 0 })
-        .sum()
+fn part2<N: CheckedCount>(graph: &BagGraph, goal: ColorId) -> eyre::Result<N> {
+    graph.bags_inside(goal)
 }
-fn can_contain(rules: &HashMap<String, Vec<(String, usize)>>, color: &str, other: &str) -> bool {
-    if let Some(contents) = rules.get(color) {
-        if contents.iter().any(|(c, _)| c == other) {
-            true
-        } else {
-            contents
-                .iter()
-                .map(|(c, _)| c)
-                .any(|color| can_contain(rules, color, other))
+
+/// Solves part 2 and lists ancestors, counting in `N`
+fn count_bags<N: CheckedCount>(
+    graph: &BagGraph,
+    goal: ColorId,
+    list_ancestors: bool,
+) -> eyre::Result<()> {
+    // Solve part 2
+    println!("Part 2: {}", part2::<N>(graph, goal)?);
+    // List everything that can hold our bag
+    if list_ancestors {
+        let mut ancestors = graph.ancestor_multiplicities::<N>(goal)?;
+        ancestors.sort_unstable_by(|(a, _), (b, _)| graph.name(*a).cmp(graph.name(*b)));
+        for (color, count) in ancestors {
+            println!("{}: {}", graph.name(color), count);
         }
-    } else {
-        false
     }
+    Ok(())
 }
 
-fn part2(rules: &HashMap<String, Vec<(String, usize)>>, goal_color: &str) -> usize {
-    bag_count(rules, goal_color) - 1
-}
-
-fn bag_count(rules: &HashMap<String, Vec<(String, usize)>>, goal_color: &str) -> usize {
-    match rules.get(goal_color) {
-        Some(contents) => {
-            if contents.is_empty() {
-                1
-            } else {
-                contents
-                    .iter()
-                    .map(|(color, count)| count * bag_count(rules, color))
-                    .sum::<usize>()
-                    + 1
-            }
+/// Prints the lints and statistics for a rule set
+fn print_analysis(graph: &BagGraph, analysis: &Analysis) {
+    let names = |colors: &[ColorId]| {
+        colors
+            .iter()
+            .map(|color| graph.name(*color))
+            .collect::<Vec<_>>()
+    };
+    println!("Undefined colors ({}):", analysis.undefined.len());
+    for color in names(&analysis.undefined) {
+        println!("    {}", color);
+    }
+    println!("Duplicate definitions ({}):", analysis.duplicates.len());
+    for (color, lines) in &analysis.duplicates {
+        let lines = lines.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        println!("    {} on lines {}", graph.name(*color), lines.join(", "));
+    }
+    // Reachability is only checked from a goal
+    if let Some(unreachable) = &analysis.unreachable {
+        println!("Unreachable colors ({}):", unreachable.len());
+        for color in names(unreachable) {
+            println!("    {}", color);
+        }
+    }
+    println!("Roots: {}", analysis.roots.len());
+    println!("Leaves: {}", analysis.leaves.len());
+    match &analysis.cycle {
+        Some(cycle) => println!("Cycle: {}", names(cycle).join(" -> ")),
+        None => println!("Cycle: none"),
+    }
+    // Chains and orders only exist without a cycle
+    if let Some(chain) = &analysis.longest_chain {
+        println!(
+            "Longest chain ({}): {}",
+            chain.len(),
+            names(chain).join(" -> ")
+        );
+    }
+    if let Some(order) = &analysis.topological_order {
+        println!("Topological order:");
+        for color in names(order) {
+            println!("    {}", color);
         }
-        None => 0,
     }
 }
 
@@ -87,39 +94,93 @@ const GOAL_COLOR: &str = "shiny gold";
 
 fn main() -> eyre::Result<()> {
     color_eyre::install()?;
+    // Parse arguments
+    let mut goal_color = GOAL_COLOR.to_owned();
+    let mut list_ancestors = false;
+    let mut export = None;
+    let mut highlight = false;
+    let mut bigint = false;
+    let mut canonical = false;
+    let mut analyze = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--color" => {
+                goal_color = args
+                    .next()
+                    .ok_or_else(|| eyre::eyre!("--color requires a value"))?
+            }
+            "--ancestors" => list_ancestors = true,
+            "--export" => {
+                export = Some(
+                    args.next()
+                        .ok_or_else(|| eyre::eyre!("--export requires a value"))?
+                        .parse::<GraphFormat>()?,
+                )
+            }
+            "--highlight" => highlight = true,
+            "--bigint" => bigint = true,
+            "--canonical" => canonical = true,
+            "--analyze" => analyze = true,
+            other => return Err(eyre::eyre!("Unknown argument: {}", other)),
+        }
+    }
     let stdin = io::stdin();
     // Start the timer
     let start_time = Instant::now();
-    // Create the map
-    let rules: HashMap<String, Vec<(String, usize)>> = stdin
+    // Read the rules
+    let rules: Vec<Rule> = stdin
         .lock()
         .lines()
-        .map(|line| {
-            line.wrap_err("Failed to read line").and_then(|line| {
-                let mut parts = line.trim_end_matches('.').split(" bags contain ");
-                let outer_bag = parts
-                    .next()
-                    .ok_or_else(|| eyre::eyre!("Missing containing bag"))?
-                    .to_owned();
-                let inner_bags = parts
-                    .next()
-                    .ok_or_else(|| eyre::eyre!("Missing contained bag"))?;
-                if parts.next().is_some() {
-                    return Err(eyre::eyre!("Extra info at end"));
-                }
-                let inner_bags: Vec<(String, usize)> = inner_bags
-                    .split(", ")
-                    .map(parse_bag_contents_item)
-                    .collect::<Result<Option<_>, _>>()?
-                    .unwrap_or_else(Vec::new);
-                Ok((outer_bag, inner_bags))
-            })
+        .enumerate()
+        .map(|(i, line)| {
+            line.wrap_err("Failed to read line")
+                .and_then(|line| parse_rule(&line, i + 1))
         })
         .collect::<eyre::Result<_>>()?;
+    // Print the rules back out in canonical form
+    if canonical {
+        for rule in &rules {
+            println!("{}", rule);
+        }
+        return Ok(());
+    }
+    // Build the graph
+    let mut graph = BagGraph::new();
+    for rule in &rules {
+        graph.add_rule(&rule.color, &rule.contents);
+    }
+    // Linting and exporting work on any rule set, so the goal is only required when it's used
+    let goal = graph.id(&goal_color).filter(|goal| graph.is_defined(*goal));
+    let require_goal = || goal.ok_or_else(|| eyre::eyre!("No rule defines {} bags", goal_color));
+    // Lint the rules instead of solving
+    if analyze {
+        print_analysis(&graph, &Analysis::new(&rules, &graph, goal));
+        return Ok(());
+    }
+    // Exporting prints only the graph so it can be piped into other tools
+    if let Some(format) = export {
+        let highlight = if highlight {
+            Some(require_goal()?)
+        } else {
+            None
+        };
+        print!("{}", graph.export(format, highlight));
+        return Ok(());
+    }
+    let goal = require_goal()?;
     // Solve part 1
-    println!("Part 1: {}", part1(&rules, GOAL_COLOR));
-    // Solve part 2
-    println!("Part 2: {}", part2(&rules, GOAL_COLOR));
+    println!("Part 1: {}", part1(&graph, goal));
+    if bigint {
+        #[cfg(feature = "bigint")]
+        count_bags::<num_bigint::BigUint>(&graph, goal, list_ancestors)?;
+        #[cfg(not(feature = "bigint"))]
+        return Err(eyre::eyre!(
+            "--bigint requires building with the bigint feature"
+        ));
+    } else {
+        count_bags::<usize>(&graph, goal, list_ancestors)?;
+    }
     // Stop the timer
     let time_elapsed = start_time.elapsed();
     println!("Solved in {:?}", time_elapsed);
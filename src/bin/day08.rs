@@ -11,148 +11,144 @@
 //
 // You should have received a copy of the GNU General Public
 // License along with this program. If not, see <https://www.gnu.org/licenses/>.
+use aoc2020::vm::{
+    Budget, ControlFlowGraph, Goal, InstructionSet, Machine, RunResult, SearchSpace, Syntax,
+    Termination,
+};
 use color_eyre::eyre::{self, WrapErr};
-use std::convert::TryInto;
-use std::io::{self, BufRead};
-use std::str::FromStr;
-use std::time::Instant;
+use std::io::{self, Read};
+use std::time::{Duration, Instant};
 
-/// Represents a single line of "assembly" code
-#[derive(Debug)]
-enum Instruction {
-    Acc(i64),
-    Jmp(isize),
-    Nop(isize),
-}
-
-impl Instruction {
-    /// Flips jmp and nop
-    ///
-    /// # Returns
-    /// `true` if the instruction was flipped
-    /// `false` if the instruction was not flipped
-    fn flip(&mut self) -> bool {
-        match self {
-            Self::Acc(_) => false,
-            Self::Jmp(value) => {
-                *self = Self::Nop(*value);
-                true
-            }
-            Self::Nop(value) => {
-                *self = Self::Jmp(*value);
-                true
-            }
-        }
+/// Describes where an out of bounds jump goes
+fn describe_target(target: Option<isize>) -> String {
+    match target {
+        Some(target) => target.to_string(),
+        None => "a pc that overflows".to_string(),
     }
 }
 
-impl FromStr for Instruction {
-    type Err = eyre::Report;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut tokens = s.split(' ');
-        let op = tokens.next().ok_or_else(|| eyre::eyre!("Missing op"))?;
-        match op {
-            "acc" => tokens
-                .next()
-                .ok_or_else(|| eyre::eyre!("Missing value"))?
-                .parse()
-                .map(Self::Acc)
-                .wrap_err("Failed to parse acc argument"),
-            "jmp" => tokens
-                .next()
-                .ok_or_else(|| eyre::eyre!("Missing value"))?
-                .parse()
-                .map(Self::Jmp)
-                .wrap_err("Failed to parse acc argument"),
-            "nop" => tokens
-                .next()
-                .ok_or_else(|| eyre::eyre!("Missing value"))?
-                .parse()
-                .map(Self::Nop)
-                .wrap_err("Failed to parse acc argument"),
-            other => Err(eyre::eyre!("Invalid op: {}", other)),
-        }
+/// Prints what static analysis found out about a program
+fn print_analysis(cfg: &ControlFlowGraph) {
+    println!("Basic blocks: {}", cfg.blocks.len());
+    for (b, block) in cfg.blocks.iter().enumerate() {
+        println!(
+            "    b{}: {}..{} -> {:?}",
+            b, block.start, block.end, block.successor
+        );
     }
-}
-
-/// Represents a machine that contains code
-struct Machine {
-    code: Vec<Instruction>,
-}
-
-/// Represents the result of running code
-#[derive(Debug)]
-enum RunResult {
-    InfiniteLoop(i64),
-    Complete(i64),
-    TerminatedAbnormally(std::num::TryFromIntError),
-}
-
-impl Machine {
-    /// Machine constructor
-    fn new(code: Vec<Instruction>) -> Self {
-        Self { code }
+    let dead = cfg.dead_code();
+    println!("Dead instructions: {}", dead.len());
+    let loops = cfg.loops();
+    println!("Loops: {}", loops.len());
+    for blocks in loops {
+        let blocks = blocks.iter().map(|b| format!("b{}", b)).collect::<Vec<_>>();
+        println!("    {}", blocks.join(" -> "));
     }
-    /// Runs the machine's code
-    fn run(&self) -> RunResult {
-        let mut pc = 0;
-        let mut acc = 0;
-        let mut visited = vec![false; self.code.len()];
-        loop {
-            // Try converting pc to usize
-            let pc_u: usize = match pc.try_into() {
-                Ok(pc_u) => pc_u,
-                Err(err) => break RunResult::TerminatedAbnormally(err),
-            };
-            if pc_u >= self.code.len() {
-                break RunResult::Complete(acc);
-            }
-            if visited[pc_u] {
-                break RunResult::InfiniteLoop(acc);
-            }
-            // Mark instruction as visited
-            visited[pc_u] = true;
-            // Run instruction
-            pc += match self.code[pc_u] {
-                Instruction::Acc(value) => {
-                    acc += value;
-                    1
-                }
-                Instruction::Jmp(value) => value,
-                Instruction::Nop(_) => 1,
-            }
-        }
+    for (from, target) in cfg.out_of_bounds_jumps() {
+        println!(
+            "Out of bounds: instruction {} jumps to {}",
+            from,
+            describe_target(target)
+        );
     }
-    /// Flips the instruction at the given index
-    ///
-    /// # Returns
-    /// Ok(true) if instruction was flipped
-    /// Ok(false) if instruction was not flipped
-    /// Err(err) if index went out of bounds
-    fn flip_instruction(&mut self, index: usize) -> eyre::Result<bool> {
-        self.code
-            .get_mut(index)
-            .map(Instruction::flip)
-            .ok_or_else(|| eyre::eyre!("Index {} is out of bounds", index))
+    match cfg.termination() {
+        Termination::Completes => println!("Terminates: the path from b0 reaches the end"),
+        Termination::Loops(blocks) => println!(
+            "Infinite loop: the path from b0 enters the loop at b{}",
+            blocks[0]
+        ),
+        Termination::OutOfBounds { from, target } => println!(
+            "Faults: instruction {} on the path from b0 jumps to {}",
+            from,
+            describe_target(target)
+        ),
     }
 }
 
 fn main() -> eyre::Result<()> {
     color_eyre::install()?;
-    let stdin = io::stdin();
+    // Parse arguments
+    let mut analyze = false;
+    let mut dot = false;
+    let mut budget = Budget::default();
+    let mut detect_loops = true;
+    let mut syntax = Syntax::Strict;
+    let mut search = false;
+    let mut space = SearchSpace::default();
+    let mut goal = Goal::Terminates;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |name| {
+            args.next()
+                .ok_or_else(|| eyre::eyre!("{} requires a value", name))
+        };
+        match arg.as_str() {
+            "--analyze" => analyze = true,
+            "--dot" => dot = true,
+            "--max-steps" => {
+                budget.steps = Some(
+                    value("--max-steps")?
+                        .parse()
+                        .wrap_err("Invalid step count")?,
+                )
+            }
+            "--timeout-ms" => {
+                budget.timeout = Some(Duration::from_millis(
+                    value("--timeout-ms")?.parse().wrap_err("Invalid timeout")?,
+                ))
+            }
+            "--no-loop-detection" => detect_loops = false,
+            "--lenient" => syntax = Syntax::Lenient,
+            "--search" => {
+                search = true;
+                space.max_edits = value("--search")?.parse().wrap_err("Invalid edit count")?;
+            }
+            "--delta" => space.max_delta = value("--delta")?.parse().wrap_err("Invalid delta")?,
+            "--delete" => space.delete = true,
+            "--no-flip" => space.flip = false,
+            "--goal" => goal = value("--goal")?.parse()?,
+            other => return Err(eyre::eyre!("Unknown argument: {}", other)),
+        }
+    }
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .wrap_err("Failed to read input")?;
     // Start the timer
     let start_time = Instant::now();
     // Create the machine
-    let mut machine = stdin
-        .lock()
-        .lines()
-        .map(|line| match line {
-            Ok(line) => line.parse().wrap_err("Failed to parse opcode"),
-            Err(err) => Err(err).wrap_err("Failed to read line"),
-        })
-        .collect::<Result<_, _>>()
+    let mut machine = InstructionSet::new()
+        .parse_program(&input, syntax)
         .map(Machine::new)?;
+    machine.set_budget(budget);
+    machine.set_detect_loops(detect_loops);
+    // Static analysis replaces solving
+    if analyze || dot {
+        let cfg = ControlFlowGraph::new(machine.code())?;
+        if dot {
+            print!("{}", cfg.to_dot(&machine));
+        } else {
+            print_analysis(&cfg);
+        }
+        return Ok(());
+    }
+    // Searching for edits replaces solving
+    if search {
+        let solutions = machine.search(&space, |result| goal.matches(result));
+        if solutions.is_empty() {
+            println!("No edits within the search space reach the goal");
+        }
+        for solution in solutions {
+            let edits = solution
+                .edits
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            println!("{}: {:?}", edits.join(", "), solution.result);
+        }
+        println!("Searched in {:?}", start_time.elapsed());
+        return Ok(());
+    }
     // Part 1
     match machine.run() {
         RunResult::InfiniteLoop(value) => println!("Part 1: {}", value),
@@ -164,16 +160,22 @@ fn main() -> eyre::Result<()> {
         }
     }
     // Part 2
-    // Flip each instruction
-    for i in 0..machine.code.len() {
-        if let Ok(true) = machine.flip_instruction(i) {
-            // See if the program returns normally
-            if let RunResult::Complete(value) = machine.run() {
-                println!("Part 2: {}", value);
-                break;
-            }
-            // Flip the instruction back
-            machine.flip_instruction(i)?;
+    let repair = machine
+        .repair()?
+        .ok_or_else(|| eyre::eyre!("No single flip makes the program complete"))?;
+    println!(
+        "Part 2: {} (flipped instruction {})",
+        repair.acc, repair.index
+    );
+    // Check against trying every flip
+    if cfg!(debug_assertions) {
+        let brute_force = machine.repair_brute_force();
+        if brute_force != Some(repair) {
+            return Err(eyre::eyre!(
+                "Brute force found {:?} instead of {:?}",
+                brute_force,
+                repair
+            ));
         }
     }
     // Stop the timer
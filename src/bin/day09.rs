@@ -0,0 +1,74 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+use aoc2020::xmas::{contiguous_sum, first_invalid, weakness};
+use color_eyre::eyre::{self, WrapErr};
+use std::io::{self, BufRead};
+use std::time::Instant;
+
+/// Preamble length used by the puzzle input
+const DEFAULT_PREAMBLE: usize = 25;
+
+fn main() -> eyre::Result<()> {
+    color_eyre::install()?;
+    // Parse arguments
+    let mut preamble = DEFAULT_PREAMBLE;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--preamble" => {
+                preamble = args
+                    .next()
+                    .ok_or_else(|| eyre::eyre!("--preamble requires a value"))?
+                    .parse()
+                    .wrap_err("Invalid preamble length")?
+            }
+            other => return Err(eyre::eyre!("Unknown argument: {}", other)),
+        }
+    }
+    if preamble == 0 {
+        return Err(eyre::eyre!("Preamble length must be positive"));
+    }
+    let stdin = io::stdin();
+    let numbers = stdin
+        .lock()
+        .lines()
+        .enumerate()
+        .map(|(i, line)| match line {
+            Ok(line) => line
+                .parse()
+                .wrap_err_with(|| format!("Failed to parse line {} {:?} as integer", i + 1, line)),
+            Err(err) => Err(err).wrap_err("Failed to read line"),
+        })
+        .collect::<Result<Vec<u64>, _>>()?;
+    // Start the timer
+    let start_time = Instant::now();
+    // Part 1
+    let (index, invalid) = first_invalid(&numbers, preamble).ok_or_else(|| {
+        eyre::eyre!(
+            "Every number is the sum of two of the {} before it",
+            preamble
+        )
+    })?;
+    println!("Part 1: {} (line {})", invalid, index + 1);
+    // Part 2
+    let run = contiguous_sum(&numbers, invalid)
+        .ok_or_else(|| eyre::eyre!("No contiguous run adds up to {}", invalid))?;
+    let part2 = weakness(run).ok_or_else(|| eyre::eyre!("Weakness overflowed"))?;
+    println!("Part 2: {} ({} numbers)", part2, run.len());
+    // Stop the timer
+    let time_elapsed = start_time.elapsed();
+    println!("Solved in {:?}", time_elapsed);
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+use aoc2020::adapters::Chain;
+use aoc2020::count::CheckedCount;
+use color_eyre::eyre::{self, WrapErr};
+use std::io::{self, BufRead};
+use std::time::Instant;
+
+/// Prints the number of arrangements, counted in `N`
+fn part2<N: CheckedCount>(chain: &Chain) -> eyre::Result<()> {
+    let arrangements = chain
+        .arrangements::<N>()
+        .ok_or_else(|| eyre::eyre!("Arrangement count overflowed, try --bigint"))?;
+    println!("Part 2: {}", arrangements);
+    Ok(())
+}
+
+fn main() -> eyre::Result<()> {
+    color_eyre::install()?;
+    // Parse arguments
+    let mut histogram = false;
+    let mut bigint = false;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--histogram" => histogram = true,
+            "--bigint" => bigint = true,
+            other => return Err(eyre::eyre!("Unknown argument: {}", other)),
+        }
+    }
+    let stdin = io::stdin();
+    let adapters = stdin
+        .lock()
+        .lines()
+        .enumerate()
+        .map(|(i, line)| match line {
+            Ok(line) => line
+                .parse()
+                .wrap_err_with(|| format!("Failed to parse line {} {:?} as integer", i + 1, line)),
+            Err(err) => Err(err).wrap_err("Failed to read line"),
+        })
+        .collect::<Result<Vec<u64>, _>>()?;
+    // Start the timer
+    let start_time = Instant::now();
+    let chain = Chain::new(adapters)?;
+    // Part 1
+    let differences = chain.histogram();
+    if histogram {
+        for (diff, count) in differences.iter().enumerate().skip(1) {
+            println!("{} jolt differences: {}", diff, count);
+        }
+    }
+    println!("Part 1: {}", differences[1] * differences[3]);
+    // Part 2
+    if bigint {
+        #[cfg(feature = "bigint")]
+        part2::<num_bigint::BigUint>(&chain)?;
+        #[cfg(not(feature = "bigint"))]
+        return Err(eyre::eyre!(
+            "--bigint requires building with the bigint feature"
+        ));
+    } else {
+        part2::<usize>(&chain)?;
+    }
+    // Stop the timer
+    let time_elapsed = start_time.elapsed();
+    println!("Solved in {:?}", time_elapsed);
+
+    Ok(())
+}
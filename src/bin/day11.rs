@@ -0,0 +1,178 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+use aoc2020::grid::{Grid, DIRECTIONS};
+use color_eyre::eyre::{self, WrapErr};
+use std::io::{self, Read};
+use std::time::Instant;
+
+/// A spot in the waiting area
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Cell {
+    Floor,
+    Empty,
+    Occupied,
+}
+
+impl Cell {
+    fn parse(c: char) -> Option<Self> {
+        match c {
+            '.' => Some(Self::Floor),
+            'L' => Some(Self::Empty),
+            '#' => Some(Self::Occupied),
+            _ => None,
+        }
+    }
+    fn render(&self) -> char {
+        match self {
+            Self::Floor => '.',
+            Self::Empty => 'L',
+            Self::Occupied => '#',
+        }
+    }
+}
+
+/// Which seats a person pays attention to
+#[derive(Clone, Copy, Debug)]
+enum Visibility {
+    /// The 8 cells around them
+    Adjacent,
+    /// The first seat in each of the 8 directions, looking past floor
+    LineOfSight,
+}
+
+/// How people decide whether to sit down or get up
+#[derive(Clone, Copy, Debug)]
+struct Rule {
+    visibility: Visibility,
+    /// A person leaves once they can see at least this many occupied seats
+    tolerance: usize,
+}
+
+/// Seats that each seat pays attention to, by index into the grid's cells
+///
+/// Seats never move, so this is worked out once instead of every round. Floor has no neighbours
+fn neighbours(grid: &Grid<Cell>, visibility: Visibility) -> Vec<Vec<usize>> {
+    let is_seat = |(x, y): &(usize, usize)| grid.get(*x, *y) != Some(&Cell::Floor);
+    (0..grid.cells().len())
+        .map(|index| {
+            if grid.cells()[index] == Cell::Floor {
+                return Vec::new();
+            }
+            let (x, y) = grid.position(index);
+            let seats: Vec<(usize, usize)> = match visibility {
+                Visibility::Adjacent => grid.neighbours(x, y).filter(is_seat).collect(),
+                Visibility::LineOfSight => DIRECTIONS
+                    .iter()
+                    .filter_map(|(dx, dy)| grid.ray(x, y, *dx, *dy).find(is_seat))
+                    .collect(),
+            };
+            seats
+                .into_iter()
+                .filter_map(|(x, y)| grid.index(x, y))
+                .collect()
+        })
+        .collect()
+}
+
+/// Runs rounds until nobody moves, calling `frame` with each layout along the way
+///
+/// # Returns
+/// The number of rounds in which someone moved, and the number of occupied seats at the end
+fn simulate<F: FnMut(usize, &Grid<Cell>)>(
+    grid: &Grid<Cell>,
+    rule: Rule,
+    mut frame: F,
+) -> (usize, usize) {
+    let neighbours = neighbours(grid, rule.visibility);
+    let mut current = grid.clone();
+    let mut next = grid.clone();
+    let mut rounds = 0;
+    frame(rounds, &current);
+    loop {
+        let mut changed = false;
+        for (index, cell) in next.cells_mut().iter_mut().enumerate() {
+            let occupied = neighbours[index]
+                .iter()
+                .filter(|seat| current.cells()[**seat] == Cell::Occupied)
+                .count();
+            *cell = match current.cells()[index] {
+                Cell::Empty if occupied == 0 => Cell::Occupied,
+                Cell::Occupied if occupied >= rule.tolerance => Cell::Empty,
+                other => other,
+            };
+            changed |= *cell != current.cells()[index];
+        }
+        // Converged once a round changes nothing
+        if !changed {
+            break;
+        }
+        std::mem::swap(&mut current, &mut next);
+        rounds += 1;
+        frame(rounds, &current);
+    }
+    let occupied = current
+        .cells()
+        .iter()
+        .filter(|cell| **cell == Cell::Occupied)
+        .count();
+    (rounds, occupied)
+}
+
+fn main() -> eyre::Result<()> {
+    color_eyre::install()?;
+    // Parse arguments
+    let mut frames = false;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--frames" => frames = true,
+            other => return Err(eyre::eyre!("Unknown argument: {}", other)),
+        }
+    }
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .wrap_err("Failed to read input")?;
+    // Start the timer
+    let start_time = Instant::now();
+    let grid = Grid::parse(&input, Cell::parse)?;
+    let parts = [
+        Rule {
+            visibility: Visibility::Adjacent,
+            tolerance: 4,
+        },
+        Rule {
+            visibility: Visibility::LineOfSight,
+            tolerance: 5,
+        },
+    ];
+    for (part, rule) in parts.iter().enumerate() {
+        let (rounds, occupied) = simulate(&grid, *rule, |round, layout| {
+            if frames {
+                println!("Part {}, round {}:", part + 1, round);
+                println!("{}", layout.render(Cell::render));
+            }
+        });
+        println!(
+            "Part {}: {} (stable after {} rounds)",
+            part + 1,
+            occupied,
+            rounds
+        );
+    }
+    // Stop the timer
+    let time_elapsed = start_time.elapsed();
+    println!("Solved in {:?}", time_elapsed);
+
+    Ok(())
+}
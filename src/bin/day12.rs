@@ -0,0 +1,92 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+use aoc2020::navigation::{
+    manhattan, navigate, path_ascii, path_svg, Heading, Instruction, Waypoint,
+};
+use color_eyre::eyre::{self, WrapErr};
+use std::io::{self, BufRead};
+use std::time::Instant;
+
+/// Size of the ASCII drawing of a path
+const ASCII_COLUMNS: usize = 80;
+const ASCII_ROWS: usize = 40;
+
+/// How to draw a path
+enum Drawing {
+    Svg,
+    Ascii,
+}
+
+fn main() -> eyre::Result<()> {
+    color_eyre::install()?;
+    // Parse arguments
+    let mut drawing = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut part = |name| -> eyre::Result<usize> {
+            match args.next().as_deref() {
+                Some("1") => Ok(1),
+                Some("2") => Ok(2),
+                Some(other) => Err(eyre::eyre!("Invalid part for {}: {}", name, other)),
+                None => Err(eyre::eyre!("{} requires a part", name)),
+            }
+        };
+        match arg.as_str() {
+            "--svg" => drawing = Some((Drawing::Svg, part("--svg")?)),
+            "--ascii" => drawing = Some((Drawing::Ascii, part("--ascii")?)),
+            other => return Err(eyre::eyre!("Unknown argument: {}", other)),
+        }
+    }
+    let stdin = io::stdin();
+    let instructions = stdin
+        .lock()
+        .lines()
+        .enumerate()
+        .map(|(i, line)| match line {
+            Ok(line) => line
+                .parse::<Instruction>()
+                .wrap_err_with(|| format!("Line {}: failed to parse {:?}", i + 1, line)),
+            Err(err) => Err(err).wrap_err("Failed to read line"),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    // Start the timer
+    let start_time = Instant::now();
+    let paths = [
+        navigate(&mut Heading::default(), &instructions)?,
+        navigate(&mut Waypoint::default(), &instructions)?,
+    ];
+    // Drawing an SVG replaces solving
+    if let Some((Drawing::Svg, part)) = drawing {
+        print!("{}", path_svg(&paths[part - 1]));
+        return Ok(());
+    }
+    for (part, path) in paths.iter().enumerate() {
+        // Paths always start where the ship began
+        let end = path[path.len() - 1];
+        let distance =
+            manhattan(end).ok_or_else(|| eyre::eyre!("Manhattan distance overflowed"))?;
+        println!("Part {}: {}", part + 1, distance);
+    }
+    // Stop the timer
+    let time_elapsed = start_time.elapsed();
+    println!("Solved in {:?}", time_elapsed);
+    if let Some((Drawing::Ascii, part)) = drawing {
+        print!(
+            "{}",
+            path_ascii(&paths[part - 1], ASCII_COLUMNS, ASCII_ROWS)
+        );
+    }
+
+    Ok(())
+}
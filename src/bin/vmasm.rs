@@ -0,0 +1,41 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+use aoc2020::vm::{assemble, disassemble, InstructionSet, Syntax};
+use color_eyre::eyre::{self, WrapErr};
+use std::io::{self, Read};
+
+fn main() -> eyre::Result<()> {
+    color_eyre::install()?;
+    let mode = std::env::args().nth(1);
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .wrap_err("Failed to read input")?;
+    let instruction_set = InstructionSet::new();
+    match mode.as_deref() {
+        // Source with labels to a raw program
+        Some("asm") => {
+            for instruction in assemble(&input, &instruction_set)? {
+                println!("{}", instruction_set.display(&instruction));
+            }
+        }
+        // Raw program to source with labels
+        Some("disasm") => {
+            let code = instruction_set.parse_program(&input, Syntax::Lenient)?;
+            print!("{}", disassemble(&code, &instruction_set));
+        }
+        _ => return Err(eyre::eyre!("Usage: vmasm asm|disasm < input")),
+    }
+    Ok(())
+}
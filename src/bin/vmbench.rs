@@ -0,0 +1,133 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+use aoc2020::vm::{Backend, Budget, InstructionSet, Machine, Syntax};
+use color_eyre::eyre::{self, WrapErr};
+use std::fs;
+use std::time::{Duration, Instant};
+
+/// Number of times each benchmark runs when not given
+const DEFAULT_ITERATIONS: u32 = 100;
+
+/// Programs for `--check`, between them reaching every way a run can end
+const CHECK_PROGRAMS: [(&str, &str); 6] = [
+    ("completes", "acc +3\nnop +0\njmp +1\n"),
+    ("loops", "acc +1\njmp -1\n"),
+    ("negative pc", "nop +0\njmp -2\n"),
+    ("overshoot", "acc +1\njmp +3\n"),
+    ("acc overflow", "acc +9223372036854775807\nacc +1\n"),
+    ("pc overflow", "nop +0\njmp +9223372036854775807\n"),
+];
+
+/// Steps allowed in `--check` runs without loop detection, so looping programs use it up
+const CHECK_STEPS: usize = 100;
+
+/// Runs `f` `iterations` times
+///
+/// # Returns
+/// The average time taken by each call
+fn time<T, F: FnMut() -> T>(iterations: u32, mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        // Keep the optimizer from throwing the result away
+        std::hint::black_box(f());
+    }
+    start.elapsed() / iterations
+}
+
+/// Runs every check program on both backends, with and without loop detection
+///
+/// # Errors
+/// If the backends disagree on any of them
+fn check() -> eyre::Result<()> {
+    let instruction_set = InstructionSet::new();
+    for (name, source) in CHECK_PROGRAMS.iter() {
+        let mut machine = instruction_set
+            .parse_program(source, Syntax::Strict)
+            .map(Machine::new)
+            .wrap_err_with(|| format!("Failed to load the {} program", name))?;
+        for detect_loops in [true, false] {
+            machine.set_detect_loops(detect_loops);
+            machine.set_budget(Budget {
+                steps: if detect_loops {
+                    None
+                } else {
+                    Some(CHECK_STEPS)
+                },
+                ..Budget::default()
+            });
+            let mut results = Vec::new();
+            for backend in [Backend::Interpreter, Backend::Compiled] {
+                machine.set_backend(backend);
+                results.push((machine.run(), machine.repair_brute_force()));
+            }
+            if results[0] != results[1] {
+                return Err(eyre::eyre!(
+                    "Backends disagree on {} (loop detection {}): {:?} and {:?}",
+                    name,
+                    detect_loops,
+                    results[0],
+                    results[1]
+                ));
+            }
+            println!(
+                "{} (loop detection {}): {:?}",
+                name, detect_loops, results[0].0
+            );
+        }
+    }
+    Ok(())
+}
+
+fn main() -> eyre::Result<()> {
+    color_eyre::install()?;
+    let mut args = std::env::args().skip(1);
+    let path = args
+        .next()
+        .ok_or_else(|| eyre::eyre!("Usage: vmbench <program> [iterations] or vmbench --check"))?;
+    if path == "--check" {
+        return check();
+    }
+    let iterations = match args.next() {
+        Some(n) => n.parse().wrap_err("Invalid iteration count")?,
+        None => DEFAULT_ITERATIONS,
+    };
+    if iterations == 0 {
+        return Err(eyre::eyre!("Iteration count must be positive"));
+    }
+    let source = fs::read_to_string(&path).wrap_err_with(|| format!("Failed to read {}", path))?;
+    let mut machine = InstructionSet::new()
+        .parse_program(&source, Syntax::Strict)
+        .map(Machine::new)
+        .wrap_err_with(|| format!("Failed to load {}", path))?;
+    let mut results = Vec::new();
+    for backend in [Backend::Interpreter, Backend::Compiled] {
+        machine.set_backend(backend);
+        let run = time(iterations, || machine.run());
+        let brute_force = time(iterations, || machine.repair_brute_force());
+        println!(
+            "{:?}: run {:?}, brute force part 2 {:?}",
+            backend, run, brute_force
+        );
+        results.push((machine.run(), machine.repair_brute_force()));
+    }
+    // Both backends have to agree for the timings to mean anything
+    if results[0] != results[1] {
+        return Err(eyre::eyre!(
+            "Backends disagree: {:?} and {:?}",
+            results[0],
+            results[1]
+        ));
+    }
+    Ok(())
+}
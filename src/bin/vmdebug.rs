@@ -0,0 +1,204 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+use aoc2020::vm::{Debugger, InstructionSet, Machine, Stop, Syntax};
+use color_eyre::eyre::{self, WrapErr};
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "\
+Commands:
+    s, step [n]       run n instructions (default 1)
+    b, back [n]       undo n instructions (default 1)
+    c, continue       run until a breakpoint, watch, loop or the end
+    break <pc>        set a breakpoint
+    delete <pc>       remove a breakpoint
+    breakpoints       list breakpoints
+    watch             toggle stopping when acc changes
+    l, list [radius]  show instructions around pc (default 3)
+    r, regs           show pc and registers
+    visited           show instructions that have already run
+    reset             go back to the start, keeping breakpoints
+    h, help           show this message
+    q, quit           exit";
+
+/// Prints why the debugger stopped
+fn print_stop(debugger: &Debugger, stop: &Stop) {
+    match stop {
+        Stop::Stepped(_) => {}
+        Stop::Breakpoint(index) => println!("Breakpoint at {}", index),
+        Stop::Watch { index, old, new } => {
+            println!("acc changed from {} to {} at {}", old, new, index)
+        }
+        Stop::Loop(index) => println!(
+            "Instruction {} already ran, run() would report an infinite loop here",
+            index
+        ),
+        Stop::Complete => println!("Program complete with acc {}", debugger.state().acc()),
+        Stop::Fault(fault) => println!("Fault at pc {}: {}", debugger.state().pc, fault),
+    }
+}
+
+/// Prints the instructions around pc, marking pc and breakpoints
+fn print_window(debugger: &Debugger, radius: usize) {
+    let pc = debugger.state().pc;
+    for (i, instruction) in debugger.window(radius) {
+        let pc_mark = if pc >= 0 && pc as usize == i {
+            '>'
+        } else {
+            ' '
+        };
+        let break_mark = if debugger.breakpoints().contains(&i) {
+            '*'
+        } else {
+            ' '
+        };
+        println!(
+            "{}{} {:>5}  {}",
+            pc_mark,
+            break_mark,
+            i,
+            debugger.machine().instruction_set().display(instruction)
+        );
+    }
+}
+
+/// Parses an optional numeric argument
+fn parse_arg(arg: Option<&str>, default: usize) -> eyre::Result<usize> {
+    arg.map_or(Ok(default), |arg| {
+        arg.parse()
+            .wrap_err_with(|| format!("Invalid number: {}", arg))
+    })
+}
+
+/// Runs a single command
+///
+/// # Returns
+/// `false` if the debugger should exit
+fn run_command(debugger: &mut Debugger, line: &str) -> eyre::Result<bool> {
+    let mut tokens = line.split_whitespace();
+    let command = match tokens.next() {
+        Some(command) => command,
+        None => return Ok(true),
+    };
+    let arg = tokens.next();
+    match command {
+        "s" | "step" => {
+            for _ in 0..parse_arg(arg, 1)? {
+                let stop = debugger.step();
+                if let Stop::Stepped(_) = stop {
+                } else {
+                    print_stop(debugger, &stop);
+                    break;
+                }
+            }
+            print_window(debugger, 0);
+        }
+        "b" | "back" => {
+            for _ in 0..parse_arg(arg, 1)? {
+                if !debugger.step_back() {
+                    println!("At the start of history");
+                    break;
+                }
+            }
+            print_window(debugger, 0);
+        }
+        "c" | "continue" => {
+            let stop = debugger.resume();
+            print_stop(debugger, &stop);
+            print_window(debugger, 0);
+        }
+        "break" => {
+            let index = parse_arg(arg, usize::MAX)?;
+            if index >= debugger.machine().code().len() {
+                return Err(eyre::eyre!("break requires a pc inside the program"));
+            }
+            if !debugger.add_breakpoint(index) {
+                println!("Breakpoint already set at {}", index);
+            }
+        }
+        "delete" => {
+            if !debugger.remove_breakpoint(parse_arg(arg, usize::MAX)?) {
+                println!("No breakpoint there");
+            }
+        }
+        "breakpoints" => {
+            for index in debugger.breakpoints() {
+                println!("{}", index);
+            }
+        }
+        "watch" => {
+            let watch = !debugger.watch_acc();
+            debugger.set_watch_acc(watch);
+            println!("Watching acc: {}", watch);
+        }
+        "l" | "list" => print_window(debugger, parse_arg(arg, 3)?),
+        "r" | "regs" => {
+            let state = debugger.state();
+            println!("pc: {}", state.pc);
+            for (i, value) in state.registers.iter().enumerate() {
+                println!("r{}: {}", i, value);
+            }
+            println!("steps: {}", debugger.history_len());
+        }
+        "visited" => {
+            let visited = debugger
+                .visited()
+                .iter()
+                .enumerate()
+                .filter(|(_, v)| **v)
+                .map(|(i, _)| i.to_string())
+                .collect::<Vec<_>>();
+            println!("{} visited: {}", visited.len(), visited.join(", "));
+        }
+        "reset" => {
+            debugger.reset();
+            print_window(debugger, 0);
+        }
+        "h" | "help" => println!("{}", HELP),
+        "q" | "quit" => return Ok(false),
+        other => println!("Unknown command {:?}, try help", other),
+    }
+    Ok(true)
+}
+
+fn main() -> eyre::Result<()> {
+    color_eyre::install()?;
+    let path = std::env::args()
+        .nth(1)
+        .ok_or_else(|| eyre::eyre!("Usage: vmdebug <program>"))?;
+    let source = fs::read_to_string(&path).wrap_err_with(|| format!("Failed to read {}", path))?;
+    // Create the machine
+    let machine = InstructionSet::new()
+        .parse_program(&source, Syntax::Strict)
+        .map(Machine::new)
+        .wrap_err_with(|| format!("Failed to load {}", path))?;
+    let mut debugger = Debugger::new(&machine);
+    print_window(&debugger, 0);
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(vm) ");
+        io::stdout().flush().wrap_err("Failed to flush prompt")?;
+        let line = match lines.next() {
+            Some(line) => line.wrap_err("Failed to read command")?,
+            None => break,
+        };
+        match run_command(&mut debugger, &line) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(err) => println!("Error: {}", err),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,129 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+use aoc2020::vm::{InstructionSet, Machine, Syntax, Trace, TraceEntry, TraceFormat};
+use color_eyre::eyre::{self, WrapErr};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+
+const USAGE: &str = "\
+Usage:
+    vmtrace record <program> <trace> [jsonl|bin]
+    vmtrace show <trace>
+    vmtrace replay <program> <trace>
+    vmtrace diff <trace> <trace>";
+
+/// Number of steps shown on each side of the first difference
+const DIFF_CONTEXT: usize = 3;
+
+fn load_machine(path: &str) -> eyre::Result<Machine> {
+    let source = fs::read_to_string(path).wrap_err_with(|| format!("Failed to read {}", path))?;
+    InstructionSet::new()
+        .parse_program(&source, Syntax::Strict)
+        .map(Machine::new)
+        .wrap_err_with(|| format!("Failed to load {}", path))
+}
+
+fn load_trace(path: &str) -> eyre::Result<Trace> {
+    let file = File::open(path).wrap_err_with(|| format!("Failed to open {}", path))?;
+    Trace::read(BufReader::new(file)).wrap_err_with(|| format!("Failed to load {}", path))
+}
+
+fn format_entry(entry: Option<&TraceEntry>) -> String {
+    match entry {
+        Some(entry) => format!(
+            "{:>5}  {:<9} acc {} -> {}",
+            entry.pc,
+            entry.instruction.to_string(),
+            entry.acc_before,
+            entry.acc_after
+        ),
+        None => "(end)".into(),
+    }
+}
+
+fn main() -> eyre::Result<()> {
+    color_eyre::install()?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["record", program, path, rest @ ..] => {
+            let format = match rest {
+                [] => TraceFormat::Jsonl,
+                [format] => format.parse()?,
+                _ => return Err(eyre::eyre!("{}", USAGE)),
+            };
+            let machine = load_machine(program)?;
+            let mut trace = Trace::default();
+            let result = machine.run_traced(&mut trace);
+            let file = File::create(path).wrap_err_with(|| format!("Failed to create {}", path))?;
+            trace.write(BufWriter::new(file), format)?;
+            println!("Recorded {} steps, {:?}", trace.entries.len(), result);
+        }
+        ["show", path] => {
+            let trace = load_trace(path)?;
+            for (step, entry) in trace.entries.iter().enumerate() {
+                println!("{:>7}  {}", step, format_entry(Some(entry)));
+            }
+            match trace.outcome {
+                Some(outcome) => println!("Outcome: {:?}", outcome),
+                None => println!("Outcome: not recorded"),
+            }
+        }
+        ["replay", program, path] => {
+            let state = load_trace(path)?.replay(&load_machine(program)?)?;
+            println!(
+                "Replay matches, ending at pc {} with acc {}",
+                state.pc,
+                state.acc()
+            );
+        }
+        ["diff", a, b] => {
+            let (a, b) = (load_trace(a)?, load_trace(b)?);
+            match a.diff(&b) {
+                None => println!(
+                    "Traces are identical ({} steps, {:?})",
+                    a.entries.len(),
+                    a.outcome
+                ),
+                Some(step) => {
+                    if step == a.entries.len() && step == b.entries.len() {
+                        println!("Traces have the same {} steps but end differently", step);
+                    } else {
+                        println!("Traces diverge at step {}", step);
+                    }
+                    let start = step.saturating_sub(DIFF_CONTEXT);
+                    for i in start..=step + DIFF_CONTEXT {
+                        let (left, right) = (a.entries.get(i), b.entries.get(i));
+                        if left.is_none() && right.is_none() {
+                            break;
+                        }
+                        let mark = if left == right { ' ' } else { '!' };
+                        println!(
+                            "{} {:>7}  {:<40} | {}",
+                            mark,
+                            i,
+                            format_entry(left),
+                            format_entry(right)
+                        );
+                    }
+                    if a.outcome != b.outcome {
+                        println!("! outcome  {:?} | {:?}", a.outcome, b.outcome);
+                    }
+                }
+            }
+        }
+        _ => return Err(eyre::eyre!("{}", USAGE)),
+    }
+    Ok(())
+}
@@ -0,0 +1,56 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Counting with numbers that report overflow instead of wrapping, as used by days 7 and 10
+
+/// Number type that counts can be kept in
+///
+/// Every operation is checked, returning `None` if the result doesn't fit
+pub trait CheckedCount: Clone + std::fmt::Display {
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn checked_mul_count(&self, count: usize) -> Option<Self>;
+}
+
+impl CheckedCount for usize {
+    fn zero() -> Self {
+        0
+    }
+    fn one() -> Self {
+        1
+    }
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        usize::checked_add(*self, *other)
+    }
+    fn checked_mul_count(&self, count: usize) -> Option<Self> {
+        self.checked_mul(count)
+    }
+}
+
+/// Arbitrary precision counts, which can never overflow
+#[cfg(feature = "bigint")]
+impl CheckedCount for num_bigint::BigUint {
+    fn zero() -> Self {
+        Self::from(0u8)
+    }
+    fn one() -> Self {
+        Self::from(1u8)
+    }
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self + other)
+    }
+    fn checked_mul_count(&self, count: usize) -> Option<Self> {
+        Some(self * count)
+    }
+}
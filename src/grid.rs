@@ -0,0 +1,164 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Rectangular grids of cells, as used by days 3 and 11
+use color_eyre::eyre;
+use std::convert::TryFrom;
+
+/// Offsets to the 8 cells surrounding a cell, as `(dx, dy)`
+pub const DIRECTIONS: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Rectangular grid stored row by row in a single vector
+///
+/// Positions are `(x, y)` with `(0, 0)` in the top left corner
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    /// Parses a grid with one row per line, converting each character with `cell`
+    ///
+    /// # Errors
+    /// If `cell` rejects a character, or the rows aren't all the same length. Errors name the
+    /// 1-based line and column
+    pub fn parse<F: FnMut(char) -> Option<T>>(s: &str, mut cell: F) -> eyre::Result<Self> {
+        let mut width = None;
+        let mut height = 0;
+        let mut cells = Vec::new();
+        for (y, line) in s.lines().enumerate() {
+            let before = cells.len();
+            for (x, c) in line.chars().enumerate() {
+                cells.push(cell(c).ok_or_else(|| {
+                    eyre::eyre!(
+                        "Invalid character {:?} at line {}, column {}",
+                        c,
+                        y + 1,
+                        x + 1
+                    )
+                })?);
+            }
+            let len = cells.len() - before;
+            match width {
+                None => width = Some(len),
+                Some(width) if width != len => {
+                    return Err(eyre::eyre!(
+                        "Line {} has {} cells but line 1 has {}",
+                        y + 1,
+                        len,
+                        width
+                    ))
+                }
+                Some(_) => {}
+            }
+            height += 1;
+        }
+        Ok(Self {
+            width: width.unwrap_or(0),
+            height,
+            cells,
+        })
+    }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    /// Every cell, row by row
+    pub fn cells(&self) -> &[T] {
+        &self.cells
+    }
+    /// Every cell, row by row, for changing in place
+    pub fn cells_mut(&mut self) -> &mut [T] {
+        &mut self.cells
+    }
+    /// Index of a position into [`Grid::cells`]
+    pub fn index(&self, x: usize, y: usize) -> Option<usize> {
+        if x < self.width && y < self.height {
+            Some(y * self.width + x)
+        } else {
+            None
+        }
+    }
+    /// Position of an index into [`Grid::cells`]
+    pub fn position(&self, index: usize) -> (usize, usize) {
+        (index % self.width, index / self.width)
+    }
+    pub fn get(&self, x: usize, y: usize) -> Option<&T> {
+        self.index(x, y).map(|index| &self.cells[index])
+    }
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut T> {
+        self.index(x, y).map(move |index| &mut self.cells[index])
+    }
+    /// Moves from a position by an offset
+    ///
+    /// # Returns
+    /// `None` if the new position is off the grid
+    pub fn offset(&self, x: usize, y: usize, dx: isize, dy: isize) -> Option<(usize, usize)> {
+        let x = usize::try_from(isize::try_from(x).ok()?.checked_add(dx)?).ok()?;
+        let y = usize::try_from(isize::try_from(y).ok()?.checked_add(dy)?).ok()?;
+        self.index(x, y).map(|_| (x, y))
+    }
+    /// Positions of the up to 8 cells touching a position
+    pub fn neighbours(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        DIRECTIONS
+            .iter()
+            .filter_map(move |(dx, dy)| self.offset(x, y, *dx, *dy))
+    }
+    /// Positions in a straight line from a position, not including it, until the edge of the grid
+    ///
+    /// A zero offset would never reach the edge, so it gives no positions
+    pub fn ray(
+        &self,
+        x: usize,
+        y: usize,
+        dx: isize,
+        dy: isize,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let first = if (dx, dy) == (0, 0) {
+            None
+        } else {
+            self.offset(x, y, dx, dy)
+        };
+        std::iter::successors(first, move |(x, y)| self.offset(*x, *y, dx, dy))
+    }
+    /// Converts every cell, keeping the layout
+    pub fn map<U, F: FnMut(&T) -> U>(&self, f: F) -> Grid<U> {
+        Grid {
+            width: self.width,
+            height: self.height,
+            cells: self.cells.iter().map(f).collect(),
+        }
+    }
+    /// Draws the grid with one character per cell and one line per row
+    pub fn render<F: FnMut(&T) -> char>(&self, mut cell: F) -> String {
+        let mut out = String::with_capacity((self.width + 1) * self.height);
+        for row in self.cells.chunks(self.width.max(1)) {
+            out.extend(row.iter().map(&mut cell));
+            out.push('\n');
+        }
+        out
+    }
+}
@@ -11,3 +11,10 @@
 //
 // You should have received a copy of the GNU General Public
 // License along with this program. If not, see <https://www.gnu.org/licenses/>.
+pub mod adapters;
+pub mod bags;
+pub mod count;
+pub mod grid;
+pub mod navigation;
+pub mod vm;
+pub mod xmas;
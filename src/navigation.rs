@@ -0,0 +1,359 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Ship navigation from day 12
+//!
+//! Positions are `(east, north)`, so north is up
+use color_eyre::eyre::{self, WrapErr};
+use std::fmt;
+use std::str::FromStr;
+
+/// Position or direction as `(east, north)`
+pub type Point = (i64, i64);
+
+/// What a navigation instruction does
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    North,
+    South,
+    East,
+    West,
+    Left,
+    Right,
+    Forward,
+}
+
+impl Action {
+    /// Direction of a compass action
+    fn direction(self) -> Option<Point> {
+        match self {
+            Self::North => Some((0, 1)),
+            Self::South => Some((0, -1)),
+            Self::East => Some((1, 0)),
+            Self::West => Some((-1, 0)),
+            _ => None,
+        }
+    }
+    fn letter(self) -> char {
+        match self {
+            Self::North => 'N',
+            Self::South => 'S',
+            Self::East => 'E',
+            Self::West => 'W',
+            Self::Left => 'L',
+            Self::Right => 'R',
+            Self::Forward => 'F',
+        }
+    }
+}
+
+/// Represents a single line of navigation instructions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub action: Action,
+    pub value: i64,
+}
+
+impl Instruction {
+    /// Number of quarter turns clockwise for a turn
+    fn quarter_turns(&self) -> Option<i64> {
+        match self.action {
+            Action::Right => Some((self.value / 90).rem_euclid(4)),
+            Action::Left => Some((-(self.value / 90)).rem_euclid(4)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.action.letter(), self.value)
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        let letter = chars.next().ok_or_else(|| eyre::eyre!("Missing action"))?;
+        let action = match letter {
+            'N' => Action::North,
+            'S' => Action::South,
+            'E' => Action::East,
+            'W' => Action::West,
+            'L' => Action::Left,
+            'R' => Action::Right,
+            'F' => Action::Forward,
+            other => return Err(eyre::eyre!("Invalid action {:?}", other)),
+        };
+        let value = chars.as_str();
+        let value = value
+            .parse()
+            .wrap_err_with(|| format!("Invalid {} value {:?}", letter, value))?;
+        let instruction = Self { action, value };
+        if instruction.quarter_turns().is_some() && value % 90 != 0 {
+            return Err(eyre::eyre!(
+                "Turns must be a multiple of 90 degrees, found {}",
+                instruction
+            ));
+        }
+        Ok(instruction)
+    }
+}
+
+/// Turns a direction clockwise by some number of quarter turns
+fn rotate((east, north): Point, quarter_turns: i64) -> Option<Point> {
+    match quarter_turns.rem_euclid(4) {
+        1 => Some((north, east.checked_neg()?)),
+        2 => Some((east.checked_neg()?, north.checked_neg()?)),
+        3 => Some((north.checked_neg()?, east)),
+        _ => Some((east, north)),
+    }
+}
+
+/// Moves a point along a direction some number of times
+fn advance(point: Point, (east, north): Point, times: i64) -> Option<Point> {
+    Some((
+        point.0.checked_add(east.checked_mul(times)?)?,
+        point.1.checked_add(north.checked_mul(times)?)?,
+    ))
+}
+
+/// A way of interpreting navigation instructions
+pub trait Navigator {
+    /// Where the ship is
+    fn position(&self) -> Point;
+    /// Carries out an instruction
+    ///
+    /// # Returns
+    /// `None` if a coordinate overflowed
+    fn apply(&mut self, instruction: &Instruction) -> Option<()>;
+}
+
+/// Part 1: compass actions move the ship, and turns change its heading
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Heading {
+    pub position: Point,
+    pub heading: Point,
+}
+
+impl Default for Heading {
+    /// Starts at the origin facing east
+    fn default() -> Self {
+        Self {
+            position: (0, 0),
+            heading: (1, 0),
+        }
+    }
+}
+
+impl Navigator for Heading {
+    fn position(&self) -> Point {
+        self.position
+    }
+    fn apply(&mut self, instruction: &Instruction) -> Option<()> {
+        match (instruction.action.direction(), instruction.quarter_turns()) {
+            (Some(direction), _) => {
+                self.position = advance(self.position, direction, instruction.value)?
+            }
+            (None, Some(turns)) => self.heading = rotate(self.heading, turns)?,
+            (None, None) => {
+                self.position = advance(self.position, self.heading, instruction.value)?
+            }
+        }
+        Some(())
+    }
+}
+
+/// Part 2: compass actions and turns move a waypoint, and the ship moves towards the waypoint
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Waypoint {
+    pub position: Point,
+    /// Waypoint, relative to the ship
+    pub waypoint: Point,
+}
+
+impl Default for Waypoint {
+    /// Starts at the origin with the waypoint 10 east and 1 north
+    fn default() -> Self {
+        Self {
+            position: (0, 0),
+            waypoint: (10, 1),
+        }
+    }
+}
+
+impl Navigator for Waypoint {
+    fn position(&self) -> Point {
+        self.position
+    }
+    fn apply(&mut self, instruction: &Instruction) -> Option<()> {
+        match (instruction.action.direction(), instruction.quarter_turns()) {
+            (Some(direction), _) => {
+                self.waypoint = advance(self.waypoint, direction, instruction.value)?
+            }
+            (None, Some(turns)) => self.waypoint = rotate(self.waypoint, turns)?,
+            (None, None) => {
+                self.position = advance(self.position, self.waypoint, instruction.value)?
+            }
+        }
+        Some(())
+    }
+}
+
+/// Distance from the origin moving only along the axes
+pub fn manhattan((east, north): Point) -> Option<u64> {
+    east.unsigned_abs().checked_add(north.unsigned_abs())
+}
+
+/// Carries out every instruction in turn
+///
+/// # Returns
+/// Every position the ship was at, starting with where it began
+pub fn navigate<N: Navigator>(
+    navigator: &mut N,
+    instructions: &[Instruction],
+) -> eyre::Result<Vec<Point>> {
+    let mut path = vec![navigator.position()];
+    for (i, instruction) in instructions.iter().enumerate() {
+        navigator.apply(instruction).ok_or_else(|| {
+            eyre::eyre!(
+                "Position overflowed at instruction {} ({})",
+                i + 1,
+                instruction
+            )
+        })?;
+        path.push(navigator.position());
+    }
+    Ok(path)
+}
+
+/// Smallest and largest coordinates on a path, as `(min, max)`
+fn bounds(path: &[Point]) -> (Point, Point) {
+    path.iter().fold(
+        ((i64::MAX, i64::MAX), (i64::MIN, i64::MIN)),
+        |((min_e, min_n), (max_e, max_n)), (east, north)| {
+            (
+                (min_e.min(*east), min_n.min(*north)),
+                (max_e.max(*east), max_n.max(*north)),
+            )
+        },
+    )
+}
+
+/// A path drawn as an SVG polyline
+struct Svg<'a> {
+    path: &'a [Point],
+}
+
+impl fmt::Display for Svg<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.path;
+        if path.is_empty() {
+            return Ok(());
+        }
+        let ((min_e, min_n), (max_e, max_n)) = bounds(path);
+        // Pad the view so the markers at the ends aren't cut off
+        let width = max_e.saturating_sub(min_e).max(1);
+        let height = max_n.saturating_sub(min_n).max(1);
+        let pad = width.max(height) / 50 + 1;
+        // SVG's y axis points down, so north is flipped, saturating since i64::MIN has no negation
+        let points = path
+            .iter()
+            .map(|(east, north)| format!("{},{}", east, north.saturating_neg()))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let (start, end) = (path[0], path[path.len() - 1]);
+        writeln!(
+            f,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">",
+            min_e.saturating_sub(pad),
+            max_n.saturating_neg().saturating_sub(pad),
+            width.saturating_add(pad * 2),
+            height.saturating_add(pad * 2)
+        )?;
+        writeln!(
+            f,
+            "  <polyline points=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"{}\"/>",
+            points,
+            pad as f64 / 4.0
+        )?;
+        writeln!(
+            f,
+            "  <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"green\"/>",
+            start.0,
+            start.1.saturating_neg(),
+            pad
+        )?;
+        writeln!(
+            f,
+            "  <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"red\"/>",
+            end.0,
+            end.1.saturating_neg(),
+            pad
+        )?;
+        writeln!(f, "</svg>")
+    }
+}
+
+/// Draws a path as an SVG polyline, with the start marked green and the end red
+pub fn path_svg(path: &[Point]) -> String {
+    Svg { path }.to_string()
+}
+
+/// Draws a path as ASCII art no bigger than `columns` by `rows`
+///
+/// The path is scaled down to fit, with `S` at the start, `E` at the end, and `*` along the way
+pub fn path_ascii(path: &[Point], columns: usize, rows: usize) -> String {
+    if path.is_empty() || columns == 0 || rows == 0 {
+        return String::new();
+    }
+    let ((min_e, min_n), (max_e, max_n)) = bounds(path);
+    // Work in floating point, since the span can be bigger than an i64
+    let span_e = (max_e as f64 - min_e as f64).max(1.0);
+    let span_n = (max_n as f64 - min_n as f64).max(1.0);
+    let to_cell = |(east, north): Point| {
+        let column = (east as f64 - min_e as f64) / span_e * (columns - 1) as f64;
+        // North is up, so larger norths get smaller rows
+        let row = (max_n as f64 - north as f64) / span_n * (rows - 1) as f64;
+        (column.round() as usize, row.round() as usize)
+    };
+    let mut canvas = vec![vec![' '; columns]; rows];
+    for pair in path.windows(2) {
+        let (from, to) = (to_cell(pair[0]), to_cell(pair[1]));
+        // Enough samples to touch every cell along the segment
+        let steps = (from.0 as isize - to.0 as isize)
+            .unsigned_abs()
+            .max((from.1 as isize - to.1 as isize).unsigned_abs())
+            .max(1);
+        for step in 0..=steps {
+            let t = step as f64 / steps as f64;
+            let column = from.0 as f64 + (to.0 as f64 - from.0 as f64) * t;
+            let row = from.1 as f64 + (to.1 as f64 - from.1 as f64) * t;
+            canvas[row.round() as usize][column.round() as usize] = '*';
+        }
+    }
+    let (start, end) = (to_cell(path[0]), to_cell(path[path.len() - 1]));
+    canvas[start.1][start.0] = 'S';
+    canvas[end.1][end.0] = 'E';
+    let mut out = String::new();
+    for row in canvas {
+        out.extend(row.iter());
+        // Drop the padding on the right
+        let trimmed = out.trim_end_matches(' ').len();
+        out.truncate(trimmed);
+        out.push('\n');
+    }
+    out
+}
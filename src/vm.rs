@@ -0,0 +1,548 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Handheld game console from day 8
+mod asm;
+mod budget;
+mod cfg;
+mod debugger;
+mod fast;
+mod repair;
+mod search;
+mod trace;
+
+pub use asm::{assemble, disassemble};
+pub use budget::{Budget, CancelHandle, Exhausted};
+pub use cfg::{BasicBlock, ControlFlowGraph, Edge, Termination};
+pub use debugger::{Debugger, Stop};
+pub use fast::Backend;
+pub use repair::Repair;
+pub use search::{Edit, EditKind, Goal, SearchSpace, Solution};
+pub use trace::{Trace, TraceEntry, TraceFormat};
+
+use budget::Meter;
+use color_eyre::eyre::{self, WrapErr};
+use fast::Compiled;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// Index of a register
+pub type Register = usize;
+
+/// The accumulator, which is always register 0
+pub const ACC: Register = 0;
+
+/// Represents a single line of "assembly" code
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    Acc(i64),
+    Jmp(isize),
+    Nop(isize),
+    /// Opcode from the machine's instruction set, by index, along with its argument
+    Ext(usize, i64),
+}
+
+impl Instruction {
+    /// Flips jmp and nop
+    ///
+    /// # Returns
+    /// `true` if the instruction was flipped
+    /// `false` if the instruction was not flipped
+    pub fn flip(&mut self) -> bool {
+        match self {
+            Self::Acc(_) | Self::Ext(..) => false,
+            Self::Jmp(value) => {
+                *self = Self::Nop(*value);
+                true
+            }
+            Self::Nop(value) => {
+                *self = Self::Jmp(*value);
+                true
+            }
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    /// Writes the instruction in source form
+    ///
+    /// Extension opcodes don't know their mnemonic, so they are written as `ext<index>`. Use
+    /// [`InstructionSet::display`] to get the real mnemonic
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Acc(value) => write!(f, "acc {:+}", value),
+            Self::Jmp(value) => write!(f, "jmp {:+}", value),
+            Self::Nop(value) => write!(f, "nop {:+}", value),
+            Self::Ext(op, value) => write!(f, "ext{} {:+}", op, value),
+        }
+    }
+}
+
+/// How strictly whitespace in source lines is checked
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Syntax {
+    /// Exactly one space between the opcode and its argument, as in the puzzle input
+    Strict,
+    /// Any amount of spaces or tabs around and between tokens, and blank lines are skipped
+    Lenient,
+}
+
+/// Splits a line into its opcode and argument
+///
+/// # Errors
+/// If either token is missing, anything follows the argument, or the whitespace doesn't match
+/// the syntax
+fn split_instruction(s: &str, syntax: Syntax) -> eyre::Result<(&str, &str)> {
+    if syntax == Syntax::Strict
+        && !s.is_empty()
+        && (s.split(' ').any(str::is_empty) || s.contains(|c: char| c.is_whitespace() && c != ' '))
+    {
+        return Err(eyre::eyre!(
+            "Unexpected whitespace in {:?}, expected a single space between opcode and argument",
+            s
+        ));
+    }
+    let mut tokens = s.split_whitespace();
+    let op = tokens.next().ok_or_else(|| eyre::eyre!("Missing opcode"))?;
+    let arg = tokens
+        .next()
+        .ok_or_else(|| eyre::eyre!("Missing argument for {}", op))?;
+    match tokens.next() {
+        Some(extra) => Err(eyre::eyre!(
+            "Unexpected {:?} after {} argument {:?}",
+            extra,
+            op,
+            arg
+        )),
+        None => Ok((op, arg)),
+    }
+}
+
+/// Parses an opcode's argument, naming the opcode and the bad token if it fails
+fn parse_argument<T>(op: &str, arg: &str) -> eyre::Result<T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    arg.parse()
+        .wrap_err_with(|| format!("Invalid {} argument {:?}", op, arg))
+}
+
+impl Instruction {
+    /// Parses a line containing one of the built in opcodes
+    pub fn parse(s: &str, syntax: Syntax) -> eyre::Result<Self> {
+        let (op, arg) = split_instruction(s, syntax)?;
+        match op {
+            "acc" => parse_argument(op, arg).map(Self::Acc),
+            "jmp" => parse_argument(op, arg).map(Self::Jmp),
+            "nop" => parse_argument(op, arg).map(Self::Nop),
+            other => Err(eyre::eyre!("Invalid opcode {:?}", other)),
+        }
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = eyre::Report;
+
+    /// Parses a line using [`Syntax::Strict`]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, Syntax::Strict)
+    }
+}
+
+/// An opcode beyond the built in `acc`, `jmp` and `nop`
+pub trait Opcode: fmt::Debug {
+    /// Name of the opcode in source code
+    fn mnemonic(&self) -> &str;
+    /// Runs the opcode
+    ///
+    /// Registers should be left untouched if the opcode faults
+    ///
+    /// # Returns
+    /// The amount to move pc by
+    fn execute(&self, arg: i64, registers: &mut [i64]) -> Result<isize, Fault>;
+}
+
+/// Extension opcodes available to a machine
+#[derive(Debug, Default)]
+pub struct InstructionSet {
+    opcodes: Vec<Box<dyn Opcode>>,
+}
+
+impl InstructionSet {
+    /// InstructionSet constructor, with only the built in opcodes
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds an opcode
+    ///
+    /// # Returns
+    /// The index used by [`Instruction::Ext`] to refer to the opcode
+    pub fn register<O: Opcode + 'static>(&mut self, opcode: O) -> usize {
+        self.opcodes.push(Box::new(opcode));
+        self.opcodes.len() - 1
+    }
+    /// Gets an extension opcode by index
+    pub fn get(&self, index: usize) -> Option<&dyn Opcode> {
+        self.opcodes.get(index).map(|op| op.as_ref())
+    }
+    /// Parses a line, accepting both built in and extension opcodes
+    pub fn parse(&self, s: &str) -> eyre::Result<Instruction> {
+        self.parse_with(s, Syntax::Strict)
+    }
+    /// Parses a line with the given syntax, accepting both built in and extension opcodes
+    pub fn parse_with(&self, s: &str, syntax: Syntax) -> eyre::Result<Instruction> {
+        let (op, arg) = split_instruction(s, syntax)?;
+        match self.opcodes.iter().position(|ext| ext.mnemonic() == op) {
+            Some(index) => parse_argument(op, arg).map(|value| Instruction::Ext(index, value)),
+            None => Instruction::parse(s, syntax),
+        }
+    }
+    /// Parses a whole program, one instruction per line
+    ///
+    /// # Errors
+    /// If any line can't be parsed. Errors name the 1-based line they came from
+    pub fn parse_program(&self, source: &str, syntax: Syntax) -> eyre::Result<Vec<Instruction>> {
+        source
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| syntax == Syntax::Strict || !line.trim().is_empty())
+            .map(|(i, line)| {
+                self.parse_with(line, syntax)
+                    .wrap_err_with(|| format!("Line {}: failed to parse {:?}", i + 1, line))
+            })
+            .collect()
+    }
+    /// Writes an instruction in source form, using the real mnemonic for extension opcodes
+    pub fn display(&self, instruction: &Instruction) -> String {
+        match instruction {
+            Instruction::Ext(op, value) => match self.get(*op) {
+                Some(op) => format!("{} {:+}", op.mnemonic(), value),
+                None => instruction.to_string(),
+            },
+            other => other.to_string(),
+        }
+    }
+}
+
+/// Registers and program counter of a running machine
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct State {
+    pub pc: isize,
+    pub registers: Vec<i64>,
+}
+
+impl State {
+    /// Value of the accumulator
+    pub fn acc(&self) -> i64 {
+        self.registers[ACC]
+    }
+}
+
+/// Why a machine stopped abnormally
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// pc went below the start of the code
+    NegativePc,
+    /// pc went past the end of the code, rather than landing exactly on it
+    Overshoot,
+    /// An instruction overflowed a register or pc
+    Overflow,
+    /// An extension opcode isn't in the machine's instruction set, by index
+    UnknownOpcode(usize),
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NegativePc => write!(f, "pc is negative"),
+            Self::Overshoot => write!(f, "pc is past the end of the code"),
+            Self::Overflow => write!(f, "arithmetic overflow"),
+            Self::UnknownOpcode(op) => {
+                write!(f, "extension opcode {} is not in the instruction set", op)
+            }
+        }
+    }
+}
+
+/// Represents the result of taking a single step
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// The instruction at this index ran
+    Executed(usize),
+    /// pc is exactly at the end of the code, so there is nothing left to run
+    Complete,
+    /// Nothing ran, because pc is invalid or the instruction at pc faulted
+    Fault(Fault),
+}
+
+/// Represents the result of running code
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunResult {
+    InfiniteLoop(i64),
+    Complete(i64),
+    /// The machine faulted, with pc and acc as they were at the fault
+    ///
+    /// For pc faults, `pc` is the invalid value. Otherwise it's the instruction that faulted
+    Fault {
+        fault: Fault,
+        pc: isize,
+        acc: i64,
+    },
+    /// The run's budget ran out before the code finished, with pc and acc at that point
+    BudgetExhausted {
+        reason: Exhausted,
+        pc: isize,
+        acc: i64,
+    },
+}
+
+/// Represents a machine that contains code
+#[derive(Debug)]
+pub struct Machine {
+    code: Vec<Instruction>,
+    registers: usize,
+    instruction_set: InstructionSet,
+    budget: Budget,
+    detect_loops: bool,
+    backend: Backend,
+    /// Pre-decoded code, present when using the compiled backend on code without extensions
+    compiled: Option<Compiled>,
+    /// Number of extension opcodes in the code, which can only be compiled once there are none
+    extensions: usize,
+}
+
+impl Machine {
+    /// Machine constructor, with only the accumulator and the built in opcodes
+    pub fn new(code: Vec<Instruction>) -> Self {
+        Self::with_extensions(code, 1, InstructionSet::new())
+    }
+    /// Machine constructor with extra registers and opcodes
+    ///
+    /// There is always at least one register, the accumulator
+    pub fn with_extensions(
+        code: Vec<Instruction>,
+        registers: usize,
+        instruction_set: InstructionSet,
+    ) -> Self {
+        let compiled = Compiled::new(&code);
+        let extensions = code
+            .iter()
+            .filter(|instruction| matches!(instruction, Instruction::Ext(..)))
+            .count();
+        Self {
+            code,
+            registers: registers.max(1),
+            instruction_set,
+            budget: Budget::default(),
+            detect_loops: true,
+            backend: Backend::Compiled,
+            compiled,
+            extensions,
+        }
+    }
+    /// Sets the limits for each run
+    pub fn set_budget(&mut self, budget: Budget) {
+        self.budget = budget;
+    }
+    /// Sets whether a run stops as soon as an instruction is about to run twice
+    ///
+    /// This is on by default. Turn it off for opcodes that depend on state, where running an
+    /// instruction again doesn't mean the program is stuck, and set a budget instead
+    pub fn set_detect_loops(&mut self, detect_loops: bool) {
+        self.detect_loops = detect_loops;
+    }
+    /// Sets how [`Machine::run`] carries out a run
+    ///
+    /// The compiled backend is the default. Both give the same results
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+        self.compiled = match backend {
+            Backend::Interpreter => None,
+            Backend::Compiled => Compiled::new(&self.code),
+        };
+    }
+    /// How [`Machine::run`] carries out a run
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+    /// The machine's code
+    pub fn code(&self) -> &[Instruction] {
+        &self.code
+    }
+    /// The machine's extension opcodes
+    pub fn instruction_set(&self) -> &InstructionSet {
+        &self.instruction_set
+    }
+    /// Initial state, with pc and every register at 0
+    pub fn start(&self) -> State {
+        State {
+            pc: 0,
+            registers: vec![0; self.registers],
+        }
+    }
+    /// Finds the instruction pc points at
+    ///
+    /// # Returns
+    /// Ok(Some(index)) if pc points into the code
+    /// Ok(None) if pc is exactly at the end of the code
+    /// Err(fault) if pc is negative or past the end
+    pub fn fetch(&self, state: &State) -> Result<Option<usize>, Fault> {
+        let pc_u = usize::try_from(state.pc).map_err(|_| Fault::NegativePc)?;
+        match pc_u.cmp(&self.code.len()) {
+            std::cmp::Ordering::Less => Ok(Some(pc_u)),
+            std::cmp::Ordering::Equal => Ok(None),
+            std::cmp::Ordering::Greater => Err(Fault::Overshoot),
+        }
+    }
+    /// Runs the instruction at `index`, regardless of pc
+    ///
+    /// If the instruction faults, the state is left as it was
+    pub fn execute(&self, index: usize, state: &mut State) -> Result<(), Fault> {
+        // pc points into the code, so moving it forward by one can't overflow
+        match self.code[index] {
+            Instruction::Acc(value) => {
+                state.registers[ACC] = state.registers[ACC]
+                    .checked_add(value)
+                    .ok_or(Fault::Overflow)?;
+                state.pc += 1;
+            }
+            Instruction::Jmp(value) => {
+                state.pc = state.pc.checked_add(value).ok_or(Fault::Overflow)?;
+            }
+            Instruction::Nop(_) => state.pc += 1,
+            Instruction::Ext(op, value) => match self.instruction_set.get(op) {
+                Some(op) => {
+                    let mut registers = state.registers.clone();
+                    let offset = op.execute(value, &mut registers)?;
+                    state.pc = state.pc.checked_add(offset).ok_or(Fault::Overflow)?;
+                    state.registers = registers;
+                }
+                // Running it as anything else would quietly give the wrong answer
+                None => return Err(Fault::UnknownOpcode(op)),
+            },
+        }
+        Ok(())
+    }
+    /// Runs the instruction pc points at
+    pub fn step(&self, state: &mut State) -> Step {
+        match self.fetch(state) {
+            Ok(Some(index)) => match self.execute(index, state) {
+                Ok(()) => Step::Executed(index),
+                Err(fault) => Step::Fault(fault),
+            },
+            Ok(None) => Step::Complete,
+            Err(fault) => Step::Fault(fault),
+        }
+    }
+    /// Runs the machine's code until it finishes, faults, exhausts its budget, or an instruction is
+    /// about to run twice
+    pub fn run(&self) -> RunResult {
+        match &self.compiled {
+            Some(compiled) => compiled.run(&self.budget, self.detect_loops),
+            None => self.run_inner(None),
+        }
+    }
+    /// Same as [`Machine::run`], but records every step and how the run ended into `trace`
+    pub fn run_traced(&self, trace: &mut Trace) -> RunResult {
+        self.run_inner(Some(trace))
+    }
+    fn run_inner(&self, mut trace: Option<&mut Trace>) -> RunResult {
+        let mut state = self.start();
+        let mut visited = vec![false; self.code.len()];
+        let mut meter = Meter::new(&self.budget);
+        let fault = |fault, state: &State| RunResult::Fault {
+            fault,
+            pc: state.pc,
+            acc: state.acc(),
+        };
+        let result = loop {
+            let pc_u = match self.fetch(&state) {
+                Ok(Some(pc_u)) => pc_u,
+                Ok(None) => break RunResult::Complete(state.acc()),
+                Err(err) => break fault(err, &state),
+            };
+            if self.detect_loops && visited[pc_u] {
+                break RunResult::InfiniteLoop(state.acc());
+            }
+            if let Some(reason) = meter.tick() {
+                break RunResult::BudgetExhausted {
+                    reason,
+                    pc: state.pc,
+                    acc: state.acc(),
+                };
+            }
+            // Mark instruction as visited
+            visited[pc_u] = true;
+            // Run instruction
+            let acc_before = state.acc();
+            if let Err(err) = self.execute(pc_u, &mut state) {
+                break fault(err, &state);
+            }
+            if let Some(trace) = trace.as_mut() {
+                trace.entries.push(TraceEntry {
+                    pc: pc_u,
+                    instruction: self.code[pc_u],
+                    acc_before,
+                    acc_after: state.acc(),
+                });
+            }
+        };
+        if let Some(trace) = trace {
+            trace.outcome = Some(result);
+        }
+        result
+    }
+    /// Flips the instruction at the given index
+    ///
+    /// # Returns
+    /// Ok(true) if instruction was flipped
+    /// Ok(false) if instruction was not flipped
+    /// Err(err) if index went out of bounds
+    pub fn flip_instruction(&mut self, index: usize) -> eyre::Result<bool> {
+        let mut instruction = *self
+            .code
+            .get(index)
+            .ok_or_else(|| eyre::eyre!("Index {} is out of bounds", index))?;
+        let flipped = instruction.flip();
+        if flipped {
+            self.set_instruction(index, instruction);
+        }
+        Ok(flipped)
+    }
+    /// Replaces the instruction at `index`, keeping the compiled code in step
+    pub(crate) fn set_instruction(&mut self, index: usize, instruction: Instruction) {
+        if let Instruction::Ext(..) = self.code[index] {
+            self.extensions -= 1;
+        }
+        if let Instruction::Ext(..) = instruction {
+            self.extensions += 1;
+        }
+        self.code[index] = instruction;
+        if self.backend == Backend::Compiled {
+            let updated = match &mut self.compiled {
+                Some(compiled) => compiled.set(index, instruction),
+                None => false,
+            };
+            // Decoding the whole program again only helps once the last extension opcode is gone
+            if !updated {
+                self.compiled = if self.extensions == 0 {
+                    Compiled::new(&self.code)
+                } else {
+                    None
+                };
+            }
+        }
+    }
+}
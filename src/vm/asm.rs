@@ -0,0 +1,173 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Assembler and disassembler for programs with labels and comments
+//!
+//! ```text
+//! ; Comments start with ';' or '#' and run to the end of the line
+//! start:
+//!     acc +1
+//! loop: jmp end   ; jmp and nop can take a label instead of an offset
+//!     jmp loop
+//! end:
+//! ```
+//!
+//! Labels compile down to the relative offsets the machine uses. A label may come after the last
+//! instruction, which is how a program jumps to its end
+use super::{Instruction, InstructionSet};
+use color_eyre::eyre::{self, WrapErr};
+use fnv::FnvHashMap;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Checks whether a string can be used as a label
+fn is_label(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+/// Splits a line into its label, if any, and the instruction after it, with comments removed
+fn split_line(line: &str) -> (Option<&str>, &str) {
+    let line = match line.find([';', '#']) {
+        Some(comment) => &line[..comment],
+        None => line,
+    };
+    let line = line.trim();
+    match line.find(':') {
+        Some(colon) => (Some(line[..colon].trim()), line[colon + 1..].trim()),
+        None => (None, line),
+    }
+}
+
+/// Assembles source code into instructions
+///
+/// # Errors
+/// If a line can't be parsed, or a label is invalid, defined twice or never defined. Errors name
+/// the 1-based line they came from
+pub fn assemble(source: &str, instruction_set: &InstructionSet) -> eyre::Result<Vec<Instruction>> {
+    // First pass finds where each label points
+    let mut labels: FnvHashMap<&str, usize> = FnvHashMap::default();
+    let mut lines = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let (label, rest) = split_line(line);
+        if let Some(label) = label {
+            if !is_label(label) {
+                return Err(eyre::eyre!("Line {}: invalid label {:?}", i + 1, label));
+            }
+            if labels.insert(label, lines.len()).is_some() {
+                return Err(eyre::eyre!("Line {}: label {} defined twice", i + 1, label));
+            }
+        }
+        if !rest.is_empty() {
+            lines.push((i + 1, rest));
+        }
+    }
+    // Second pass resolves labels into offsets
+    lines
+        .iter()
+        .enumerate()
+        .map(|(index, (line, text))| {
+            let mut tokens = text.split_whitespace();
+            let op = tokens.next().unwrap_or("");
+            let arg = tokens.next();
+            let text = match (op, arg) {
+                ("jmp", Some(arg)) | ("nop", Some(arg)) if is_label(arg) => {
+                    let target = labels
+                        .get(arg)
+                        .ok_or_else(|| eyre::eyre!("Line {}: undefined label {}", line, arg))?;
+                    let offset = *target as isize - index as isize;
+                    let rest = tokens.collect::<Vec<_>>().join(" ");
+                    format!("{} {:+} {}", op, offset, rest)
+                        .trim_end()
+                        .to_owned()
+                }
+                _ => text.split_whitespace().collect::<Vec<_>>().join(" "),
+            };
+            instruction_set
+                .parse(&text)
+                .wrap_err_with(|| format!("Line {}: failed to assemble {:?}", line, text))
+        })
+        .collect()
+}
+
+/// Where a `jmp` or `nop` at `index` lands, if that's inside the code or right at its end
+fn label_target(code: &[Instruction], index: usize, offset: isize) -> Option<usize> {
+    (index as isize)
+        .checked_add(offset)
+        .and_then(|target| usize::try_from(target).ok())
+        .filter(|target| *target <= code.len())
+}
+
+/// Source code for a program, with a name for every labelled index
+struct Disassembly<'a> {
+    code: &'a [Instruction],
+    instruction_set: &'a InstructionSet,
+    names: Vec<Option<String>>,
+}
+
+impl fmt::Display for Disassembly<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, instruction) in self.code.iter().enumerate() {
+            if let Some(name) = &self.names[i] {
+                writeln!(f, "{}:", name)?;
+            }
+            let label = match instruction {
+                Instruction::Jmp(offset) | Instruction::Nop(offset) => {
+                    label_target(self.code, i, *offset)
+                        .and_then(|target| self.names[target].as_ref())
+                }
+                _ => None,
+            };
+            match (instruction, label) {
+                (Instruction::Jmp(_), Some(label)) => writeln!(f, "    jmp {}", label)?,
+                (Instruction::Nop(_), Some(label)) => writeln!(f, "    nop {}", label)?,
+                _ => writeln!(f, "    {}", self.instruction_set.display(instruction))?,
+            }
+        }
+        if let Some(name) = &self.names[self.code.len()] {
+            writeln!(f, "{}:", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Turns instructions back into source code
+///
+/// Every `jmp` and `nop` whose target is inside the code, or right at its end, gets a label, so
+/// assembling the result gives back the same instructions
+pub fn disassemble(code: &[Instruction], instruction_set: &InstructionSet) -> String {
+    // Label targets in order, so labels are numbered from the top of the program
+    let mut is_target = vec![false; code.len() + 1];
+    for (i, instruction) in code.iter().enumerate() {
+        if let Instruction::Jmp(offset) | Instruction::Nop(offset) = instruction {
+            if let Some(target) = label_target(code, i, *offset) {
+                is_target[target] = true;
+            }
+        }
+    }
+    let mut names = vec![None; code.len() + 1];
+    for (n, index) in (0..=code.len()).filter(|i| is_target[*i]).enumerate() {
+        names[index] = Some(format!("l{}", n));
+    }
+    Disassembly {
+        code,
+        instruction_set,
+        names,
+    }
+    .to_string()
+}
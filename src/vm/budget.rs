@@ -0,0 +1,103 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Limits on how long a run may go on for
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Number of steps between checks of the clock and the cancel flag
+const CHECK_INTERVAL: usize = 1024;
+
+/// Flag for stopping a run from elsewhere, such as another thread
+///
+/// Clones share the same flag
+#[derive(Clone, Debug, Default)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    /// CancelHandle constructor
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Asks every run using this handle to stop
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Limits for a single run, all of which are off by default
+#[derive(Clone, Debug, Default)]
+pub struct Budget {
+    /// Most instructions the run may execute
+    pub steps: Option<usize>,
+    /// Longest the run may take
+    pub timeout: Option<Duration>,
+    /// Handle that stops the run when cancelled
+    pub cancel: Option<CancelHandle>,
+}
+
+/// Which part of a budget ran out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Exhausted {
+    Steps,
+    Timeout,
+    Cancelled,
+}
+
+/// Tracks a budget over a run
+///
+/// The clock and cancel flag are only looked at every [`CHECK_INTERVAL`] steps, so they stop the
+/// run slightly late in exchange for not slowing down every step
+#[derive(Debug)]
+pub(crate) struct Meter<'a> {
+    budget: &'a Budget,
+    deadline: Option<Instant>,
+    steps: usize,
+}
+
+impl<'a> Meter<'a> {
+    pub(crate) fn new(budget: &'a Budget) -> Self {
+        Self {
+            budget,
+            deadline: budget.timeout.map(|timeout| Instant::now() + timeout),
+            steps: 0,
+        }
+    }
+    /// Counts a step about to be taken
+    ///
+    /// # Returns
+    /// `Some` if the budget has run out, in which case the step shouldn't be taken
+    pub(crate) fn tick(&mut self) -> Option<Exhausted> {
+        if Some(self.steps) == self.budget.steps {
+            return Some(Exhausted::Steps);
+        }
+        if self.steps.is_multiple_of(CHECK_INTERVAL) {
+            if let Some(cancel) = &self.budget.cancel {
+                if cancel.is_cancelled() {
+                    return Some(Exhausted::Cancelled);
+                }
+            }
+            if let Some(deadline) = self.deadline {
+                if Instant::now() >= deadline {
+                    return Some(Exhausted::Timeout);
+                }
+            }
+        }
+        self.steps += 1;
+        None
+    }
+}
@@ -0,0 +1,305 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Control-flow graph of a program, for analyzing it without running it
+//!
+//! Every built in instruction has exactly one successor, so each block does too. That means the
+//! path from the first block is fixed: it either leaves the code or ends up in a loop
+use super::{Instruction, Machine};
+use color_eyre::eyre;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Where control goes after a block
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    /// Index of the next block
+    Block(usize),
+    /// pc lands exactly on the end of the code
+    Exit,
+    /// pc lands before the start or past the end of the code, or `None` if it overflowed
+    OutOfBounds(Option<isize>),
+}
+
+/// Run of instructions that always execute together, from start to end
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: usize,
+    /// Index just past the last instruction
+    pub end: usize,
+    pub successor: Edge,
+}
+
+/// What the program will do when run, worked out without running it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Termination {
+    /// pc lands exactly on the end of the code
+    Completes,
+    /// Control reaches a loop, made of these blocks in order
+    Loops(Vec<usize>),
+    /// The instruction at `from` sends pc to `target`, outside the code, or `None` if it overflowed
+    OutOfBounds { from: usize, target: Option<isize> },
+}
+
+/// Control-flow graph of a program
+#[derive(Clone, Debug)]
+pub struct ControlFlowGraph {
+    pub blocks: Vec<BasicBlock>,
+    /// Index of the block holding each instruction
+    block_of: Vec<usize>,
+}
+
+impl ControlFlowGraph {
+    /// Splits code into basic blocks
+    ///
+    /// # Errors
+    /// If the code uses extension opcodes, whose successors can't be known without running them
+    pub fn new(code: &[Instruction]) -> eyre::Result<Self> {
+        let len = code.len();
+        // Instructions that start a block
+        let mut leaders = vec![false; len];
+        let mut targets = Vec::with_capacity(len);
+        for (i, instruction) in code.iter().enumerate() {
+            let target = match instruction {
+                Instruction::Acc(_) | Instruction::Nop(_) => (i as isize).checked_add(1),
+                Instruction::Jmp(offset) => {
+                    // A jump ends a block, so whatever follows starts a new one
+                    if let Some(next) = leaders.get_mut(i + 1) {
+                        *next = true;
+                    }
+                    let target = (i as isize).checked_add(*offset);
+                    // Falling through never starts a block, but landing from a jump does
+                    if let Some(leader) = target
+                        .and_then(|t| usize::try_from(t).ok())
+                        .and_then(|t| leaders.get_mut(t))
+                    {
+                        *leader = true;
+                    }
+                    target
+                }
+                Instruction::Ext(..) => {
+                    return Err(eyre::eyre!(
+                        "Can't analyze extension opcode at instruction {}",
+                        i
+                    ))
+                }
+            };
+            targets.push(target);
+        }
+        if let Some(first) = leaders.first_mut() {
+            *first = true;
+        }
+        let mut block_of = vec![0; len];
+        let mut starts = Vec::new();
+        for (i, leader) in leaders.iter().enumerate() {
+            if *leader {
+                starts.push(i);
+            }
+            block_of[i] = starts.len() - 1;
+        }
+        let blocks = starts
+            .iter()
+            .enumerate()
+            .map(|(b, start)| {
+                let end = starts.get(b + 1).copied().unwrap_or(len);
+                let target = targets[end - 1];
+                let successor = match target.map(usize::try_from) {
+                    Some(Ok(target)) if target < len => Edge::Block(block_of[target]),
+                    Some(Ok(target)) if target == len => Edge::Exit,
+                    _ => Edge::OutOfBounds(target),
+                };
+                BasicBlock {
+                    start: *start,
+                    end,
+                    successor,
+                }
+            })
+            .collect();
+        Ok(Self { blocks, block_of })
+    }
+    /// Index of the block holding an instruction
+    pub fn block_of(&self, index: usize) -> usize {
+        self.block_of[index]
+    }
+    /// Blocks in the order they run, starting from the first block, up to and including the
+    /// first repeated block
+    fn path(&self) -> Vec<usize> {
+        let mut seen = vec![false; self.blocks.len()];
+        let mut path = Vec::new();
+        let mut edge = if self.blocks.is_empty() {
+            Edge::Exit
+        } else {
+            Edge::Block(0)
+        };
+        while let Edge::Block(block) = edge {
+            path.push(block);
+            if seen[block] {
+                break;
+            }
+            seen[block] = true;
+            edge = self.blocks[block].successor;
+        }
+        path
+    }
+    /// Works out whether the program completes, loops or jumps out of bounds
+    pub fn termination(&self) -> Termination {
+        let path = self.path();
+        match path.last() {
+            None => Termination::Completes,
+            Some(last) => match self.blocks[*last].successor {
+                Edge::Exit => Termination::Completes,
+                Edge::OutOfBounds(target) => Termination::OutOfBounds {
+                    from: self.blocks[*last].end - 1,
+                    target,
+                },
+                Edge::Block(_) => {
+                    // The last block is the repeat, so the loop starts where it first appeared
+                    let start = path.iter().position(|b| b == last).unwrap_or(0);
+                    Termination::Loops(path[start..path.len() - 1].to_vec())
+                }
+            },
+        }
+    }
+    /// Which blocks can run, starting from the first block
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.blocks.len()];
+        for block in self.path() {
+            reachable[block] = true;
+        }
+        reachable
+    }
+    /// Indices of instructions that can never run
+    pub fn dead_code(&self) -> Vec<usize> {
+        let reachable = self.reachable();
+        self.blocks
+            .iter()
+            .zip(reachable)
+            .filter(|(_, reachable)| !reachable)
+            .flat_map(|(block, _)| block.start..block.end)
+            .collect()
+    }
+    /// Every loop in the program, whether or not it can be reached, as blocks in order
+    pub fn loops(&self) -> Vec<Vec<usize>> {
+        // 0 is unvisited, otherwise the number of the walk that first reached the block
+        let mut walk_of = vec![0; self.blocks.len()];
+        let mut loops = Vec::new();
+        for start in 0..self.blocks.len() {
+            if walk_of[start] != 0 {
+                continue;
+            }
+            let walk = start + 1;
+            let mut path = Vec::new();
+            let mut block = start;
+            loop {
+                if walk_of[block] == walk {
+                    // Found a block from this walk again, so everything since it is a loop
+                    let first = path.iter().position(|b| *b == block).unwrap_or(0);
+                    loops.push(path[first..].to_vec());
+                    break;
+                }
+                if walk_of[block] != 0 {
+                    // Joined an earlier walk, whose loops are already known
+                    break;
+                }
+                walk_of[block] = walk;
+                path.push(block);
+                match self.blocks[block].successor {
+                    Edge::Block(next) => block = next,
+                    _ => break,
+                }
+            }
+        }
+        loops
+    }
+    /// Jumps whose target is outside the code, as the instruction index and its target, which is
+    /// `None` if it overflowed
+    ///
+    /// Landing exactly on the end of the code isn't counted, since that's how programs finish
+    pub fn out_of_bounds_jumps(&self) -> Vec<(usize, Option<isize>)> {
+        self.blocks
+            .iter()
+            .filter_map(|block| match block.successor {
+                Edge::OutOfBounds(target) => Some((block.end - 1, target)),
+                _ => None,
+            })
+            .collect()
+    }
+    /// Renders the graph as Graphviz DOT
+    ///
+    /// Dead blocks are grey and blocks in loops are red
+    pub fn to_dot(&self, machine: &Machine) -> String {
+        let reachable = self.reachable();
+        let mut in_loop = vec![false; self.blocks.len()];
+        for block in self.loops().into_iter().flatten() {
+            in_loop[block] = true;
+        }
+        Dot {
+            cfg: self,
+            machine,
+            reachable,
+            in_loop,
+        }
+        .to_string()
+    }
+}
+
+/// Graphviz DOT for a graph, along with which blocks to draw differently
+struct Dot<'a> {
+    cfg: &'a ControlFlowGraph,
+    machine: &'a Machine,
+    reachable: Vec<bool>,
+    in_loop: Vec<bool>,
+}
+
+impl fmt::Display for Dot<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "digraph cfg {{")?;
+        writeln!(f, "    node [shape=box, fontname=monospace];")?;
+        writeln!(f, "    exit [shape=doublecircle];")?;
+        for (b, block) in self.cfg.blocks.iter().enumerate() {
+            write!(f, "    b{} [label=\"", b)?;
+            for i in block.start..block.end {
+                let instruction = self
+                    .machine
+                    .instruction_set()
+                    .display(&self.machine.code()[i]);
+                write!(f, "{}: {}\\l", i, instruction)?;
+            }
+            let style = if !self.reachable[b] {
+                ", style=filled, fillcolor=lightgrey"
+            } else if self.in_loop[b] {
+                ", color=red"
+            } else {
+                ""
+            };
+            writeln!(f, "\"{}];", style)?;
+            match block.successor {
+                Edge::Block(next) => writeln!(f, "    b{} -> b{};", b, next)?,
+                Edge::Exit => writeln!(f, "    b{} -> exit;", b)?,
+                Edge::OutOfBounds(target) => {
+                    let label = match target {
+                        Some(target) => target.to_string(),
+                        None => "overflow".to_string(),
+                    };
+                    writeln!(
+                        f,
+                        "    oob{} [label=\"pc {}\", shape=octagon, color=red];",
+                        b, label
+                    )?;
+                    writeln!(f, "    b{} -> oob{};", b, b)?;
+                }
+            }
+        }
+        writeln!(f, "}}")
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Stepping through a machine's code with breakpoints and history
+use super::{Fault, Instruction, Machine, State, Step};
+use std::collections::BTreeSet;
+
+/// Why the debugger stopped
+#[derive(Debug)]
+pub enum Stop {
+    /// A single step finished
+    Stepped(usize),
+    /// pc reached a breakpoint, before running the instruction there
+    Breakpoint(usize),
+    /// The accumulator changed while being watched
+    Watch { index: usize, old: i64, new: i64 },
+    /// The instruction at pc has already run, which is where [`Machine::run`] reports a loop
+    Loop(usize),
+    /// pc is exactly at the end of the code
+    Complete,
+    /// pc is invalid or the instruction at pc faulted, so nothing ran
+    Fault(Fault),
+}
+
+/// One step of history, enough to undo it
+#[derive(Debug)]
+struct HistoryEntry {
+    state: State,
+    index: usize,
+    first_visit: bool,
+}
+
+/// Debugger wrapping a machine and its current state
+#[derive(Debug)]
+pub struct Debugger<'a> {
+    machine: &'a Machine,
+    state: State,
+    history: Vec<HistoryEntry>,
+    visited: Vec<bool>,
+    breakpoints: BTreeSet<usize>,
+    watch_acc: bool,
+}
+
+impl<'a> Debugger<'a> {
+    /// Debugger constructor, starting at the beginning of the code
+    pub fn new(machine: &'a Machine) -> Self {
+        Self {
+            machine,
+            state: machine.start(),
+            history: Vec::new(),
+            visited: vec![false; machine.code().len()],
+            breakpoints: BTreeSet::new(),
+            watch_acc: false,
+        }
+    }
+    /// Goes back to the beginning of the code, keeping breakpoints and watches
+    pub fn reset(&mut self) {
+        self.state = self.machine.start();
+        self.history.clear();
+        self.visited = vec![false; self.machine.code().len()];
+    }
+    pub fn machine(&self) -> &Machine {
+        self.machine
+    }
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+    /// Which instructions have run so far, as used for loop detection
+    pub fn visited(&self) -> &[bool] {
+        &self.visited
+    }
+    /// Number of steps that can be undone
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+    pub fn breakpoints(&self) -> &BTreeSet<usize> {
+        &self.breakpoints
+    }
+    /// Adds a breakpoint, returning `false` if it was already set
+    pub fn add_breakpoint(&mut self, index: usize) -> bool {
+        self.breakpoints.insert(index)
+    }
+    /// Removes a breakpoint, returning `false` if it wasn't set
+    pub fn remove_breakpoint(&mut self, index: usize) -> bool {
+        self.breakpoints.remove(&index)
+    }
+    /// Sets whether changes to the accumulator stop execution
+    pub fn set_watch_acc(&mut self, watch: bool) {
+        self.watch_acc = watch;
+    }
+    pub fn watch_acc(&self) -> bool {
+        self.watch_acc
+    }
+    /// Instructions within `radius` of pc, with their indices
+    pub fn window(&self, radius: usize) -> Vec<(usize, &Instruction)> {
+        let code = self.machine.code();
+        let center = self.state.pc.max(0) as usize;
+        let start = center.saturating_sub(radius);
+        let end = center
+            .saturating_add(radius.saturating_add(1))
+            .min(code.len());
+        (start..end).map(|i| (i, &code[i])).collect()
+    }
+    /// Runs a single instruction, whether or not it has run before
+    pub fn step(&mut self) -> Stop {
+        let before = self.state.clone();
+        match self.machine.step(&mut self.state) {
+            Step::Executed(index) => {
+                let first_visit = !self.visited[index];
+                self.visited[index] = true;
+                let old = before.acc();
+                self.history.push(HistoryEntry {
+                    state: before,
+                    index,
+                    first_visit,
+                });
+                let new = self.state.acc();
+                if self.watch_acc && old != new {
+                    Stop::Watch { index, old, new }
+                } else {
+                    Stop::Stepped(index)
+                }
+            }
+            Step::Complete => Stop::Complete,
+            Step::Fault(err) => Stop::Fault(err),
+        }
+    }
+    /// Undoes the last step
+    ///
+    /// # Returns
+    /// `false` if there was nothing to undo
+    pub fn step_back(&mut self) -> bool {
+        match self.history.pop() {
+            Some(entry) => {
+                if entry.first_visit {
+                    self.visited[entry.index] = false;
+                }
+                self.state = entry.state;
+                true
+            }
+            None => false,
+        }
+    }
+    /// Runs until a breakpoint, watch, loop, or the end of the code
+    ///
+    /// The instruction at pc always runs, so resuming from a breakpoint doesn't stop straight
+    /// away
+    pub fn resume(&mut self) -> Stop {
+        let mut first = true;
+        loop {
+            if let Ok(Some(index)) = self.machine.fetch(&self.state) {
+                if !first {
+                    if self.breakpoints.contains(&index) {
+                        break Stop::Breakpoint(index);
+                    }
+                    if self.visited[index] {
+                        break Stop::Loop(index);
+                    }
+                }
+            }
+            first = false;
+            match self.step() {
+                Stop::Stepped(_) => {}
+                stop => break stop,
+            }
+        }
+    }
+}
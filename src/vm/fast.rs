@@ -0,0 +1,178 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Faster backend for programs that only use the built in opcodes
+//!
+//! Every built in opcode adds something to the accumulator and then moves pc, so each one is
+//! decoded ahead of time into those two numbers and the run loop doesn't branch on the opcode
+use super::budget::{Budget, Meter};
+use super::{Fault, Instruction, RunResult};
+use std::cell::RefCell;
+use std::convert::TryFrom;
+
+/// How a run is carried out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// Match on each instruction as it runs
+    Interpreter,
+    /// Run pre-decoded code, falling back to the interpreter for extension opcodes
+    Compiled,
+}
+
+/// A decoded built in instruction
+#[derive(Clone, Copy, Debug)]
+struct Op {
+    /// Added to the accumulator
+    acc: i64,
+    /// Added to pc afterwards
+    jump: isize,
+}
+
+impl Op {
+    /// Decodes an instruction
+    ///
+    /// # Returns
+    /// `None` for extension opcodes
+    fn decode(instruction: Instruction) -> Option<Self> {
+        match instruction {
+            Instruction::Acc(value) => Some(Self {
+                acc: value,
+                jump: 1,
+            }),
+            Instruction::Jmp(value) => Some(Self {
+                acc: 0,
+                jump: value,
+            }),
+            Instruction::Nop(_) => Some(Self { acc: 0, jump: 1 }),
+            Instruction::Ext(..) => None,
+        }
+    }
+}
+
+/// Instructions that have already run, kept between runs
+///
+/// An instruction has run if its stamp matches the current generation, so starting a new run
+/// only bumps the generation instead of clearing every stamp
+#[derive(Debug, Default)]
+struct Visited {
+    stamps: Vec<u32>,
+    generation: u32,
+}
+
+impl Visited {
+    /// Forgets every instruction, making room for `len` of them
+    fn reset(&mut self, len: usize) {
+        self.stamps.resize(len, 0);
+        self.generation = self.generation.wrapping_add(1);
+        // Stamps from 2^32 runs ago would look current, so start over
+        if self.generation == 0 {
+            self.stamps.iter_mut().for_each(|stamp| *stamp = 0);
+            self.generation = 1;
+        }
+    }
+    /// Marks an instruction as run
+    ///
+    /// # Returns
+    /// `true` if it had already run
+    fn insert(&mut self, index: usize) -> bool {
+        let seen = self.stamps[index] == self.generation;
+        self.stamps[index] = self.generation;
+        seen
+    }
+}
+
+/// Pre-decoded code along with the visited set it reuses
+#[derive(Debug)]
+pub(crate) struct Compiled {
+    ops: Vec<Op>,
+    visited: RefCell<Visited>,
+}
+
+impl Compiled {
+    /// Decodes a program
+    ///
+    /// # Returns
+    /// `None` if the program uses extension opcodes
+    pub(crate) fn new(code: &[Instruction]) -> Option<Self> {
+        let ops = code
+            .iter()
+            .map(|instruction| Op::decode(*instruction))
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self {
+            ops,
+            visited: RefCell::new(Visited::default()),
+        })
+    }
+    /// Replaces the instruction at `index`
+    ///
+    /// # Returns
+    /// `false` if the instruction is an extension opcode, which can't be compiled
+    pub(crate) fn set(&mut self, index: usize, instruction: Instruction) -> bool {
+        match Op::decode(instruction) {
+            Some(op) => {
+                self.ops[index] = op;
+                true
+            }
+            None => false,
+        }
+    }
+    /// Runs the code, giving the same results as [`super::Machine::run`]
+    pub(crate) fn run(&self, budget: &Budget, detect_loops: bool) -> RunResult {
+        let len = self.ops.len();
+        let mut visited = self.visited.borrow_mut();
+        visited.reset(len);
+        let mut meter = Meter::new(budget);
+        let mut pc: isize = 0;
+        let mut acc: i64 = 0;
+        loop {
+            let index = match usize::try_from(pc) {
+                Ok(index) if index < len => index,
+                Ok(index) if index == len => return RunResult::Complete(acc),
+                Ok(_) => {
+                    return RunResult::Fault {
+                        fault: Fault::Overshoot,
+                        pc,
+                        acc,
+                    }
+                }
+                Err(_) => {
+                    return RunResult::Fault {
+                        fault: Fault::NegativePc,
+                        pc,
+                        acc,
+                    }
+                }
+            };
+            if visited.insert(index) && detect_loops {
+                return RunResult::InfiniteLoop(acc);
+            }
+            if let Some(reason) = meter.tick() {
+                return RunResult::BudgetExhausted { reason, pc, acc };
+            }
+            let op = self.ops[index];
+            match (acc.checked_add(op.acc), pc.checked_add(op.jump)) {
+                (Some(new_acc), Some(new_pc)) => {
+                    acc = new_acc;
+                    pc = new_pc;
+                }
+                _ => {
+                    return RunResult::Fault {
+                        fault: Fault::Overflow,
+                        pc,
+                        acc,
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Fixing a looping program by flipping a single `jmp` or `nop`
+use super::{Instruction, Machine, RunResult};
+use color_eyre::eyre;
+use std::convert::TryFrom;
+
+/// A flip that makes a program complete
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Repair {
+    /// Index of the flipped instruction
+    pub index: usize,
+    /// Accumulator once the repaired program completes
+    pub acc: i64,
+}
+
+/// Where control goes after an instruction
+///
+/// # Returns
+/// `None` if the target overflows, which is as out of bounds as a jump can get
+///
+/// # Errors
+/// For extension opcodes, which can't be followed without running them
+fn target(index: usize, instruction: Instruction) -> eyre::Result<Option<isize>> {
+    let index = index as isize;
+    match instruction {
+        Instruction::Acc(_) | Instruction::Nop(_) => Ok(index.checked_add(1)),
+        Instruction::Jmp(offset) => Ok(index.checked_add(offset)),
+        Instruction::Ext(..) => Err(eyre::eyre!("Can't repair programs with extension opcodes")),
+    }
+}
+
+impl Machine {
+    /// Finds which instructions eventually land exactly on the end of the code
+    ///
+    /// Works backwards from the end over reversed edges, so each instruction is looked at once
+    fn terminating(&self) -> eyre::Result<Vec<bool>> {
+        let len = self.code.len();
+        // Instructions that jump to each index
+        let mut sources: Vec<Vec<usize>> = vec![Vec::new(); len];
+        let mut terminating = vec![false; len];
+        let mut stack = Vec::new();
+        for (i, instruction) in self.code.iter().enumerate() {
+            match target(i, *instruction)?.map(usize::try_from) {
+                Some(Ok(target)) if target == len => {
+                    terminating[i] = true;
+                    stack.push(i);
+                }
+                Some(Ok(target)) if target < len => sources[target].push(i),
+                // Going negative, past the end or overflowing is a fault, which never completes
+                _ => {}
+            }
+        }
+        while let Some(i) = stack.pop() {
+            for source in &sources[i] {
+                if !terminating[*source] {
+                    terminating[*source] = true;
+                    stack.push(*source);
+                }
+            }
+        }
+        Ok(terminating)
+    }
+    /// Runs the code with the instruction at `index` flipped
+    fn run_flipped(&self, index: usize) -> RunResult {
+        let mut code = self.code.clone();
+        code[index].flip();
+        Machine::new(code).run()
+    }
+    /// Finds the flip that makes the program complete in linear time
+    ///
+    /// Only instructions on the original program's path matter, and flipping one of them works if
+    /// its new target is already known to reach the end
+    ///
+    /// # Returns
+    /// `Ok(None)` if no single flip works
+    pub fn repair(&self) -> eyre::Result<Option<Repair>> {
+        let terminating = self.terminating()?;
+        let len = self.code.len();
+        let mut visited = vec![false; len];
+        let mut pc = Some(0);
+        while let Some(Ok(i)) = pc.map(usize::try_from) {
+            if i >= len || visited[i] {
+                break;
+            }
+            visited[i] = true;
+            let mut flipped = self.code[i];
+            if flipped.flip() {
+                let fixed = match target(i, flipped)?.map(usize::try_from) {
+                    Some(Ok(target)) => target == len || terminating.get(target) == Some(&true),
+                    _ => false,
+                };
+                if fixed {
+                    if let RunResult::Complete(acc) = self.run_flipped(i) {
+                        return Ok(Some(Repair { index: i, acc }));
+                    }
+                }
+            }
+            pc = target(i, self.code[i])?;
+        }
+        Ok(None)
+    }
+    /// Finds the flip that makes the program complete by trying every `jmp` and `nop` in turn
+    ///
+    /// This is quadratic, but simple enough to check [`Machine::repair`] against
+    pub fn repair_brute_force(&mut self) -> Option<Repair> {
+        // Flip each instruction
+        for i in 0..self.code.len() {
+            let original = self.code[i];
+            let mut flipped = original;
+            if flipped.flip() {
+                self.set_instruction(i, flipped);
+                // See if the program returns normally
+                let result = self.run();
+                // Flip the instruction back
+                self.set_instruction(i, original);
+                if let RunResult::Complete(acc) = result {
+                    return Some(Repair { index: i, acc });
+                }
+            }
+        }
+        None
+    }
+}
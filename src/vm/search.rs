@@ -0,0 +1,261 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Searching for the smallest sets of edits that make a program reach a goal
+use super::{Instruction, Machine, RunResult, Trace};
+use color_eyre::eyre::{self, WrapErr};
+use fnv::FnvHashSet;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// A change to a single instruction
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EditKind {
+    /// Swap `jmp` and `nop`
+    Flip,
+    /// Add to the argument of an `acc`, `jmp` or extension opcode
+    Adjust(i64),
+    /// Replace the instruction with `nop +0`
+    ///
+    /// The instruction is kept in place so jumps across it still land where they did
+    Delete,
+}
+
+impl EditKind {
+    /// Applies the edit to an instruction
+    ///
+    /// # Returns
+    /// `None` if the edit doesn't apply to the instruction, or wouldn't change what it does
+    fn apply(self, instruction: Instruction) -> Option<Instruction> {
+        match (self, instruction) {
+            (Self::Flip, mut instruction) => {
+                if instruction.flip() {
+                    Some(instruction)
+                } else {
+                    None
+                }
+            }
+            (Self::Adjust(delta), Instruction::Acc(value)) => {
+                value.checked_add(delta).map(Instruction::Acc)
+            }
+            (Self::Adjust(delta), Instruction::Jmp(value)) => isize::try_from(delta)
+                .ok()
+                .and_then(|delta| value.checked_add(delta))
+                .map(Instruction::Jmp),
+            (Self::Adjust(delta), Instruction::Ext(op, value)) => value
+                .checked_add(delta)
+                .map(|value| Instruction::Ext(op, value)),
+            // A nop ignores its argument, so neither of these do anything
+            (Self::Adjust(_), Instruction::Nop(_)) | (Self::Delete, Instruction::Nop(_)) => None,
+            (Self::Delete, _) => Some(Instruction::Nop(0)),
+        }
+    }
+}
+
+/// An edit to the instruction at an index
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Edit {
+    pub index: usize,
+    pub kind: EditKind,
+}
+
+impl fmt::Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            EditKind::Flip => write!(f, "flip {}", self.index),
+            EditKind::Adjust(delta) => write!(f, "adjust {} by {:+}", self.index, delta),
+            EditKind::Delete => write!(f, "delete {}", self.index),
+        }
+    }
+}
+
+/// Which edits a search may make
+#[derive(Clone, Copy, Debug)]
+pub struct SearchSpace {
+    /// Most instructions that may be edited at once
+    pub max_edits: usize,
+    /// Whether `jmp` and `nop` may be flipped
+    pub flip: bool,
+    /// Arguments may be changed by anything from `-max_delta` to `+max_delta`
+    pub max_delta: i64,
+    /// Whether instructions may be deleted
+    pub delete: bool,
+}
+
+impl Default for SearchSpace {
+    /// Single flips, which is day 8 part 2
+    fn default() -> Self {
+        Self {
+            max_edits: 1,
+            flip: true,
+            max_delta: 0,
+            delete: false,
+        }
+    }
+}
+
+impl SearchSpace {
+    /// Every kind of edit that may be tried on an instruction
+    ///
+    /// The range of deltas can be huge, so kinds are made as they're needed rather than collected
+    fn kinds(&self) -> impl Iterator<Item = EditKind> {
+        let flip = if self.flip {
+            Some(EditKind::Flip)
+        } else {
+            None
+        };
+        let max_delta = self.max_delta.saturating_abs();
+        let delete = if self.delete {
+            Some(EditKind::Delete)
+        } else {
+            None
+        };
+        flip.into_iter()
+            .chain(
+                (-max_delta..=max_delta)
+                    .filter(|delta| *delta != 0)
+                    .map(EditKind::Adjust),
+            )
+            .chain(delete)
+    }
+}
+
+/// Common goals for a search
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Goal {
+    /// The program completes
+    Terminates,
+    /// The program completes with the accumulator at this value
+    AccEquals(i64),
+}
+
+impl Goal {
+    /// Checks whether a run reached the goal
+    pub fn matches(&self, result: &RunResult) -> bool {
+        match (self, result) {
+            (Self::Terminates, RunResult::Complete(_)) => true,
+            (Self::AccEquals(want), RunResult::Complete(acc)) => want == acc,
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Goal {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "terminates" => Ok(Self::Terminates),
+            other => match other.strip_prefix("acc=") {
+                Some(value) => value
+                    .parse()
+                    .map(Self::AccEquals)
+                    .wrap_err_with(|| format!("Invalid value in goal {:?}", other)),
+                None => Err(eyre::eyre!("Invalid goal: {}", other)),
+            },
+        }
+    }
+}
+
+/// A set of edits that reaches the goal
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Solution {
+    /// Edits, ordered by index
+    pub edits: Vec<Edit>,
+    /// How the edited program ran
+    pub result: RunResult,
+}
+
+impl Machine {
+    /// Finds every smallest set of edits that makes a run reach the goal
+    ///
+    /// Sizes are tried from zero up to `space.max_edits`, and the search stops at the first size
+    /// with any solutions. Only instructions that run can change the outcome, so each extra edit
+    /// is only tried on the path the program takes with the edits made so far. Every run is
+    /// subject to the machine's budget and loop detection
+    ///
+    /// # Returns
+    /// Solutions ordered by their edits, or nothing if no set of edits within the space works
+    pub fn search<G: Fn(&RunResult) -> bool>(
+        &mut self,
+        space: &SearchSpace,
+        goal: G,
+    ) -> Vec<Solution> {
+        for size in 0..=space.max_edits {
+            let mut found = BTreeMap::new();
+            self.search_edits(space, &goal, size, &mut Vec::new(), &mut found);
+            if !found.is_empty() {
+                return found
+                    .into_iter()
+                    .map(|(edits, result)| Solution { edits, result })
+                    .collect();
+            }
+        }
+        Vec::new()
+    }
+    /// Tries every way of adding `remaining` edits to the ones already made
+    fn search_edits<G: Fn(&RunResult) -> bool>(
+        &mut self,
+        space: &SearchSpace,
+        goal: &G,
+        remaining: usize,
+        edits: &mut Vec<Edit>,
+        found: &mut BTreeMap<Vec<Edit>, RunResult>,
+    ) {
+        if remaining == 0 {
+            let result = self.run();
+            if goal(&result) {
+                let mut edits = edits.clone();
+                edits.sort();
+                found.insert(edits, result);
+            }
+            return;
+        }
+        // Instructions on the current path, in the order they first ran
+        let mut trace = Trace::default();
+        let result = self.run_traced(&mut trace);
+        // An instruction that faults while running isn't traced, but editing it might help
+        let faulted = match result {
+            RunResult::Fault { pc, .. } => {
+                usize::try_from(pc).ok().filter(|pc| *pc < self.code.len())
+            }
+            _ => None,
+        };
+        let mut seen = FnvHashSet::default();
+        let path: Vec<usize> = trace
+            .entries
+            .iter()
+            .map(|entry| entry.pc)
+            .chain(faulted)
+            .filter(|pc| seen.insert(*pc))
+            .collect();
+        for index in path {
+            if edits.iter().any(|edit| edit.index == index) {
+                continue;
+            }
+            let original = self.code[index];
+            for kind in space.kinds() {
+                if let Some(edited) = kind.apply(original) {
+                    self.set_instruction(index, edited);
+                    edits.push(Edit { index, kind });
+                    self.search_edits(space, goal, remaining - 1, edits, found);
+                    edits.pop();
+                }
+            }
+            // Put the instruction back once every edit to it has been tried
+            self.set_instruction(index, original);
+        }
+    }
+}
@@ -0,0 +1,534 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Recording every step of a run, and saving, loading and comparing recordings
+use super::{Exhausted, Fault, Instruction, Machine, RunResult, State, Step};
+use color_eyre::eyre::{self, WrapErr};
+use std::io::{BufRead, Read, Write};
+use std::str::FromStr;
+
+/// A single executed instruction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub pc: usize,
+    pub instruction: Instruction,
+    pub acc_before: i64,
+    pub acc_after: i64,
+}
+
+/// Every instruction executed during a run, in order, and how the run ended
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Trace {
+    pub entries: Vec<TraceEntry>,
+    /// How the run ended, if it was recorded
+    pub outcome: Option<RunResult>,
+}
+
+/// File format for a saved trace
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// One JSON object per step, then one for how the run ended
+    Jsonl,
+    /// Header followed by variable length integers, with how the run ended at the end
+    Binary,
+}
+
+impl FromStr for TraceFormat {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jsonl" => Ok(Self::Jsonl),
+            "bin" | "binary" => Ok(Self::Binary),
+            other => Err(eyre::eyre!("Invalid trace format: {}", other)),
+        }
+    }
+}
+
+/// Start of every binary trace
+const MAGIC: &[u8; 4] = b"VMTR";
+/// Version of the binary format
+const VERSION: u8 = 2;
+
+/// Names of the ways a run can end, by the tag used for them in traces
+const OUTCOMES: [&str; 4] = ["complete", "loop", "fault", "budget"];
+/// Names of faults, by tag
+const FAULTS: [&str; 4] = ["negative_pc", "overshoot", "overflow", "unknown_opcode"];
+/// Names of the parts of a budget that can run out, by tag
+const REASONS: [&str; 3] = ["steps", "timeout", "cancelled"];
+
+/// Writes an unsigned LEB128 integer
+fn write_varint<W: Write>(w: &mut W, mut value: u64) -> std::io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return w.write_all(&[byte]);
+        }
+        w.write_all(&[byte | 0x80])?;
+    }
+}
+
+/// Writes a signed integer zigzag encoded, so small negative numbers stay small
+fn write_signed<W: Write>(w: &mut W, value: i64) -> std::io::Result<()> {
+    write_varint(w, ((value << 1) ^ (value >> 63)) as u64)
+}
+
+fn read_varint<R: Read>(r: &mut R) -> eyre::Result<u64> {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let mut byte = [0];
+        r.read_exact(&mut byte)
+            .wrap_err("Unexpected end of trace")?;
+        if shift >= 64 {
+            return Err(eyre::eyre!("Integer in trace is too long"));
+        }
+        value |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+        shift += 7;
+    }
+}
+
+fn read_signed<R: Read>(r: &mut R) -> eyre::Result<i64> {
+    let value = read_varint(r)?;
+    Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+}
+
+/// Splits an instruction into a tag, extension index and argument
+fn encode_instruction(instruction: Instruction) -> (u8, usize, i64) {
+    match instruction {
+        Instruction::Acc(value) => (0, 0, value),
+        Instruction::Jmp(value) => (1, 0, value as i64),
+        Instruction::Nop(value) => (2, 0, value as i64),
+        Instruction::Ext(op, value) => (3, op, value),
+    }
+}
+
+fn decode_instruction(tag: u8, op: usize, arg: i64) -> eyre::Result<Instruction> {
+    match tag {
+        0 => Ok(Instruction::Acc(arg)),
+        1 => Ok(Instruction::Jmp(arg as isize)),
+        2 => Ok(Instruction::Nop(arg as isize)),
+        3 => Ok(Instruction::Ext(op, arg)),
+        other => Err(eyre::eyre!("Invalid instruction tag in trace: {}", other)),
+    }
+}
+
+/// Splits how a run ended into a tag, the tag of the fault or budget reason, the extension index
+/// of an unknown opcode, pc and acc
+fn encode_outcome(outcome: RunResult) -> (u8, u8, usize, isize, i64) {
+    match outcome {
+        RunResult::Complete(acc) => (0, 0, 0, 0, acc),
+        RunResult::InfiniteLoop(acc) => (1, 0, 0, 0, acc),
+        RunResult::Fault { fault, pc, acc } => {
+            let (detail, op) = match fault {
+                Fault::NegativePc => (0, 0),
+                Fault::Overshoot => (1, 0),
+                Fault::Overflow => (2, 0),
+                Fault::UnknownOpcode(op) => (3, op),
+            };
+            (2, detail, op, pc, acc)
+        }
+        RunResult::BudgetExhausted { reason, pc, acc } => {
+            let detail = match reason {
+                Exhausted::Steps => 0,
+                Exhausted::Timeout => 1,
+                Exhausted::Cancelled => 2,
+            };
+            (3, detail, 0, pc, acc)
+        }
+    }
+}
+
+fn decode_outcome(tag: u8, detail: u8, op: usize, pc: isize, acc: i64) -> eyre::Result<RunResult> {
+    let invalid = || eyre::eyre!("Invalid outcome in trace: {} {}", tag, detail);
+    match tag {
+        0 => Ok(RunResult::Complete(acc)),
+        1 => Ok(RunResult::InfiniteLoop(acc)),
+        2 => {
+            let fault = match detail {
+                0 => Fault::NegativePc,
+                1 => Fault::Overshoot,
+                2 => Fault::Overflow,
+                3 => Fault::UnknownOpcode(op),
+                _ => return Err(invalid()),
+            };
+            Ok(RunResult::Fault { fault, pc, acc })
+        }
+        3 => {
+            let reason = match detail {
+                0 => Exhausted::Steps,
+                1 => Exhausted::Timeout,
+                2 => Exhausted::Cancelled,
+                _ => return Err(invalid()),
+            };
+            Ok(RunResult::BudgetExhausted { reason, pc, acc })
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Name used for an opcode in JSONL traces
+fn op_name(instruction: Instruction) -> String {
+    match instruction {
+        Instruction::Acc(_) => "acc".into(),
+        Instruction::Jmp(_) => "jmp".into(),
+        Instruction::Nop(_) => "nop".into(),
+        Instruction::Ext(op, _) => format!("ext{}", op),
+    }
+}
+
+/// Splits a single flat JSON object of the form written by [`Trace::write`] into keys and values
+fn json_fields(line: &str) -> eyre::Result<Vec<(&str, &str)>> {
+    let body = line
+        .trim()
+        .strip_prefix('{')
+        .and_then(|line| line.strip_suffix('}'))
+        .ok_or_else(|| eyre::eyre!("Expected a JSON object"))?;
+    body.split(',')
+        .map(|field| {
+            let mut parts = field.splitn(2, ':');
+            let key = parts.next().unwrap_or("").trim().trim_matches('"');
+            let value = parts
+                .next()
+                .ok_or_else(|| eyre::eyre!("Missing value for {:?}", key))?
+                .trim()
+                .trim_matches('"');
+            Ok((key, value))
+        })
+        .collect()
+}
+
+/// Parses the value of a field
+///
+/// Unknown keys, like the step number, are only there for people reading the file, so fields
+/// are looked up by name and anything else is ignored
+fn json_field<T: FromStr>(fields: &[(&str, &str)], key: &str) -> eyre::Result<T>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let value = fields
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, value)| *value)
+        .ok_or_else(|| eyre::eyre!("Missing {}", key))?;
+    value
+        .parse()
+        .wrap_err_with(|| format!("Invalid {} {:?}", key, value))
+}
+
+fn parse_json_entry(fields: &[(&str, &str)]) -> eyre::Result<TraceEntry> {
+    let op: String = json_field(fields, "op")?;
+    let arg: i64 = json_field(fields, "arg")?;
+    let instruction = match op.as_str() {
+        "acc" => decode_instruction(0, 0, arg)?,
+        "jmp" => decode_instruction(1, 0, arg)?,
+        "nop" => decode_instruction(2, 0, arg)?,
+        other => match other.strip_prefix("ext").map(str::parse) {
+            Some(Ok(index)) => Instruction::Ext(index, arg),
+            _ => return Err(eyre::eyre!("Invalid op: {}", other)),
+        },
+    };
+    Ok(TraceEntry {
+        pc: json_field(fields, "pc")?,
+        instruction,
+        acc_before: json_field(fields, "acc_before")?,
+        acc_after: json_field(fields, "acc_after")?,
+    })
+}
+
+/// Parses the line saying how the run ended
+fn parse_json_outcome(fields: &[(&str, &str)]) -> eyre::Result<RunResult> {
+    let name: String = json_field(fields, "outcome")?;
+    let tag = OUTCOMES
+        .iter()
+        .position(|outcome| *outcome == name)
+        .ok_or_else(|| eyre::eyre!("Invalid outcome: {}", name))?;
+    // Only faults and budgets say what went wrong and where
+    let (detail, op, pc) = match tag {
+        2 => {
+            let fault: String = json_field(fields, "fault")?;
+            let detail = FAULTS
+                .iter()
+                .position(|name| *name == fault)
+                .ok_or_else(|| eyre::eyre!("Invalid fault: {}", fault))?;
+            let op = if fault == "unknown_opcode" {
+                json_field(fields, "op")?
+            } else {
+                0
+            };
+            (detail, op, json_field(fields, "pc")?)
+        }
+        3 => {
+            let reason: String = json_field(fields, "reason")?;
+            let detail = REASONS
+                .iter()
+                .position(|name| *name == reason)
+                .ok_or_else(|| eyre::eyre!("Invalid budget reason: {}", reason))?;
+            (detail, 0, json_field(fields, "pc")?)
+        }
+        _ => (0, 0, 0),
+    };
+    decode_outcome(tag as u8, detail as u8, op, pc, json_field(fields, "acc")?)
+}
+
+impl Trace {
+    /// Saves the trace
+    pub fn write<W: Write>(&self, mut w: W, format: TraceFormat) -> eyre::Result<()> {
+        match format {
+            TraceFormat::Jsonl => {
+                for (step, entry) in self.entries.iter().enumerate() {
+                    let (_, _, arg) = encode_instruction(entry.instruction);
+                    writeln!(
+                        w,
+                        "{{\"step\":{},\"pc\":{},\"op\":\"{}\",\"arg\":{},\"acc_before\":{},\"acc_after\":{}}}",
+                        step,
+                        entry.pc,
+                        op_name(entry.instruction),
+                        arg,
+                        entry.acc_before,
+                        entry.acc_after
+                    )?;
+                }
+                if let Some(outcome) = self.outcome {
+                    let (tag, detail, op, pc, acc) = encode_outcome(outcome);
+                    write!(w, "{{\"outcome\":\"{}\"", OUTCOMES[tag as usize])?;
+                    match tag {
+                        2 => {
+                            let fault = FAULTS[detail as usize];
+                            write!(w, ",\"fault\":\"{}\"", fault)?;
+                            if fault == "unknown_opcode" {
+                                write!(w, ",\"op\":{}", op)?;
+                            }
+                            write!(w, ",\"pc\":{}", pc)?;
+                        }
+                        3 => write!(
+                            w,
+                            ",\"reason\":\"{}\",\"pc\":{}",
+                            REASONS[detail as usize], pc
+                        )?,
+                        _ => {}
+                    }
+                    writeln!(w, ",\"acc\":{}}}", acc)?;
+                }
+            }
+            TraceFormat::Binary => {
+                w.write_all(MAGIC)?;
+                w.write_all(&[VERSION])?;
+                write_varint(&mut w, self.entries.len() as u64)?;
+                for entry in &self.entries {
+                    let (tag, op, arg) = encode_instruction(entry.instruction);
+                    write_varint(&mut w, entry.pc as u64)?;
+                    w.write_all(&[tag])?;
+                    if tag == 3 {
+                        write_varint(&mut w, op as u64)?;
+                    }
+                    write_signed(&mut w, arg)?;
+                    write_signed(&mut w, entry.acc_before)?;
+                    // Most steps leave acc alone, so storing the change keeps them to one byte
+                    write_signed(&mut w, entry.acc_after.wrapping_sub(entry.acc_before))?;
+                }
+                match self.outcome {
+                    // 0 marks a trace without an outcome, so outcome tags are stored one higher
+                    None => w.write_all(&[0])?,
+                    Some(outcome) => {
+                        let (tag, detail, op, pc, acc) = encode_outcome(outcome);
+                        w.write_all(&[tag + 1, detail])?;
+                        write_varint(&mut w, op as u64)?;
+                        write_signed(&mut w, pc as i64)?;
+                        write_signed(&mut w, acc)?;
+                    }
+                }
+            }
+        }
+        w.flush().wrap_err("Failed to write trace")
+    }
+    /// Loads a trace, working out its format from the first bytes
+    pub fn read<R: BufRead>(mut r: R) -> eyre::Result<Self> {
+        let is_binary = r.fill_buf()?.starts_with(MAGIC);
+        let mut entries = Vec::new();
+        let mut outcome = None;
+        if is_binary {
+            let mut header = [0; 5];
+            r.read_exact(&mut header)?;
+            if header[4] != VERSION {
+                return Err(eyre::eyre!("Unsupported trace version {}", header[4]));
+            }
+            let len = read_varint(&mut r)?;
+            for i in 0..len {
+                let entry = (|| {
+                    let pc = read_varint(&mut r)? as usize;
+                    let mut tag = [0];
+                    r.read_exact(&mut tag)?;
+                    let op = if tag[0] == 3 {
+                        read_varint(&mut r)? as usize
+                    } else {
+                        0
+                    };
+                    let instruction = decode_instruction(tag[0], op, read_signed(&mut r)?)?;
+                    let acc_before = read_signed(&mut r)?;
+                    let acc_after = acc_before.wrapping_add(read_signed(&mut r)?);
+                    Ok::<_, eyre::Report>(TraceEntry {
+                        pc,
+                        instruction,
+                        acc_before,
+                        acc_after,
+                    })
+                })()
+                .wrap_err_with(|| format!("Failed to read step {}", i))?;
+                entries.push(entry);
+            }
+            outcome = (|| {
+                let mut tags = [0];
+                r.read_exact(&mut tags)?;
+                if tags[0] == 0 {
+                    return Ok(None);
+                }
+                let mut detail = [0];
+                r.read_exact(&mut detail)?;
+                let op = read_varint(&mut r)? as usize;
+                let pc = read_signed(&mut r)? as isize;
+                let acc = read_signed(&mut r)?;
+                decode_outcome(tags[0] - 1, detail[0], op, pc, acc).map(Some)
+            })()
+            .wrap_err("Failed to read the outcome")?;
+        } else {
+            for (i, line) in r.lines().enumerate() {
+                let line = line.wrap_err("Failed to read line")?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let context = || format!("Failed to parse line {}", i + 1);
+                if outcome.is_some() {
+                    return Err(eyre::eyre!("Step after the outcome")).wrap_err_with(context);
+                }
+                let fields = json_fields(&line).wrap_err_with(context)?;
+                if fields.iter().any(|(key, _)| *key == "outcome") {
+                    outcome = Some(parse_json_outcome(&fields).wrap_err_with(context)?);
+                } else {
+                    entries.push(parse_json_entry(&fields).wrap_err_with(context)?);
+                }
+            }
+        }
+        Ok(Self { entries, outcome })
+    }
+    /// Finds the first step where two traces differ
+    ///
+    /// # Returns
+    /// `None` if the traces are identical, otherwise the index of the first differing step,
+    /// which is the length of the shorter trace if one is a prefix of the other, or of both if
+    /// only the outcomes differ
+    pub fn diff(&self, other: &Self) -> Option<usize> {
+        match self
+            .entries
+            .iter()
+            .zip(&other.entries)
+            .position(|(a, b)| a != b)
+        {
+            Some(step) => Some(step),
+            None if self.entries.len() != other.entries.len() => {
+                Some(self.entries.len().min(other.entries.len()))
+            }
+            None if self.outcome != other.outcome => Some(self.entries.len()),
+            None => None,
+        }
+    }
+    /// Runs `machine` again, checking each step against the trace, and then that the machine
+    /// stops the way the trace says it did
+    ///
+    /// Budgets can't be replayed, so running out of one is taken on trust as long as the machine
+    /// was still going at that point
+    ///
+    /// # Errors
+    /// If the machine does something different from what was recorded, or the trace doesn't say
+    /// how the run ended
+    pub fn replay(&self, machine: &Machine) -> eyre::Result<State> {
+        let outcome = self.outcome.ok_or_else(|| {
+            eyre::eyre!("Trace doesn't say how the run ended, so it may have been cut short")
+        })?;
+        let mut state = machine.start();
+        let mut visited = vec![false; machine.code().len()];
+        for (step, entry) in self.entries.iter().enumerate() {
+            let acc_before = state.acc();
+            let pc = match machine.step(&mut state) {
+                Step::Executed(pc) => pc,
+                other => {
+                    return Err(eyre::eyre!(
+                        "Step {}: trace expects pc {} but machine stopped with {:?}",
+                        step,
+                        entry.pc,
+                        other
+                    ))
+                }
+            };
+            visited[pc] = true;
+            let actual = TraceEntry {
+                pc,
+                instruction: machine.code()[pc],
+                acc_before,
+                acc_after: state.acc(),
+            };
+            if actual != *entry {
+                return Err(eyre::eyre!(
+                    "Step {}: trace has {:?} but machine did {:?}",
+                    step,
+                    entry,
+                    actual
+                ));
+            }
+        }
+        // Work out what stops the machine next, checking in the same order as a run
+        let acc = state.acc();
+        let stop = match machine.fetch(&state) {
+            Ok(None) => Some(RunResult::Complete(acc)),
+            Err(fault) => Some(RunResult::Fault {
+                fault,
+                pc: state.pc,
+                acc,
+            }),
+            Ok(Some(index)) if machine.detect_loops && visited[index] => {
+                Some(RunResult::InfiniteLoop(acc))
+            }
+            Ok(Some(index)) => match (outcome, machine.execute(index, &mut state.clone())) {
+                (RunResult::BudgetExhausted { reason, .. }, _) => {
+                    Some(RunResult::BudgetExhausted {
+                        reason,
+                        pc: state.pc,
+                        acc,
+                    })
+                }
+                (_, Err(fault)) => Some(RunResult::Fault {
+                    fault,
+                    pc: state.pc,
+                    acc,
+                }),
+                (_, Ok(())) => None,
+            },
+        };
+        match stop {
+            Some(stop) if stop == outcome => Ok(state),
+            Some(stop) => Err(eyre::eyre!(
+                "Trace ends with {:?} but machine stopped with {:?}",
+                outcome,
+                stop
+            )),
+            None => Err(eyre::eyre!(
+                "Trace ends with {:?} but machine keeps running",
+                outcome
+            )),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright 2020 witchof0x20
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public
+// License along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! XMAS cipher from day 9
+use std::collections::VecDeque;
+
+/// The last few numbers of a stream, able to tell whether two of them add up to a target
+///
+/// Numbers are kept both in arrival order, to know which one to drop next, and sorted, so pairs
+/// can be found with a binary search like day 1
+#[derive(Clone, Debug)]
+pub struct PairSumWindow {
+    capacity: usize,
+    order: VecDeque<u64>,
+    sorted: Vec<u64>,
+}
+
+impl PairSumWindow {
+    /// PairSumWindow constructor, holding at most `capacity` numbers
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            order: VecDeque::with_capacity(capacity + 1),
+            sorted: Vec::with_capacity(capacity + 1),
+        }
+    }
+    /// Checks whether the window holds as many numbers as it can
+    pub fn is_full(&self) -> bool {
+        self.order.len() >= self.capacity
+    }
+    /// Adds a number, dropping the oldest one if the window is full
+    ///
+    /// # Returns
+    /// The number that was dropped, if any
+    pub fn push(&mut self, n: u64) -> Option<u64> {
+        let index = self.sorted.binary_search(&n).unwrap_or_else(|index| index);
+        self.sorted.insert(index, n);
+        self.order.push_back(n);
+        if self.order.len() > self.capacity {
+            let oldest = self.order.pop_front()?;
+            // The oldest number is in the sorted list, so the search always finds it
+            if let Ok(index) = self.sorted.binary_search(&oldest) {
+                self.sorted.remove(index);
+            }
+            Some(oldest)
+        } else {
+            None
+        }
+    }
+    /// Finds two different numbers in the window that add up to `target`
+    ///
+    /// # Returns
+    /// The pair, smaller number first
+    pub fn find_pair(&self, target: u64) -> Option<(u64, u64)> {
+        for n in &self.sorted {
+            // Numbers only get bigger from here, so none of them can be part of a pair
+            let partner = match target.checked_sub(*n) {
+                Some(partner) => partner,
+                None => break,
+            };
+            // Only look for the smaller half of each pair so the two numbers differ
+            if partner < *n && self.sorted.binary_search(&partner).is_ok() {
+                return Some((partner, *n));
+            }
+        }
+        None
+    }
+}
+
+/// Finds the first number after the preamble that isn't the sum of two of the `preamble` numbers
+/// before it
+///
+/// # Returns
+/// The index and value of the number
+pub fn first_invalid(numbers: &[u64], preamble: usize) -> Option<(usize, u64)> {
+    let mut window = PairSumWindow::new(preamble);
+    for (i, n) in numbers.iter().enumerate() {
+        if window.is_full() && window.find_pair(*n).is_none() {
+            return Some((i, *n));
+        }
+        window.push(*n);
+    }
+    None
+}
+
+/// Finds a run of at least two contiguous numbers that add up to `target`
+///
+/// Numbers are never negative, so the run can be found with a single pass of two pointers,
+/// growing it while the sum is too small and shrinking it while the sum is too big
+pub fn contiguous_sum(numbers: &[u64], target: u64) -> Option<&[u64]> {
+    let mut start = 0;
+    let mut sum: u64 = 0;
+    for (end, n) in numbers.iter().enumerate() {
+        sum = sum.checked_add(*n)?;
+        while sum > target && start < end {
+            sum -= numbers[start];
+            start += 1;
+        }
+        if sum == target && end > start {
+            return Some(&numbers[start..=end]);
+        }
+    }
+    None
+}
+
+/// Adds together the smallest and largest numbers in a run
+pub fn weakness(run: &[u64]) -> Option<u64> {
+    let min = run.iter().min()?;
+    let max = run.iter().max()?;
+    min.checked_add(*max)
+}